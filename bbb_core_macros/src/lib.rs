@@ -0,0 +1,104 @@
+extern crate bbb_core;
+extern crate proc_macro;
+extern crate proc_macro2;
+#[macro_use]
+extern crate quote;
+#[macro_use]
+extern crate syn;
+
+use bbb_core::expr::Expr;
+use bbb_core::numeral::Numeral;
+use bbb_core::ops::*;
+use bbb_core::parser::parse;
+use proc_macro2::{Literal, TokenStream};
+use syn::LitStr;
+
+/// Parses a bytebeat at compile time and expands to the
+/// `bbb_core::expr::Expr` that `bbb_core::parser::parse` would return for
+/// it, so a typo is a compile error rather than a panic at runtime:
+///
+/// ```ignore
+/// let expression: Expr = bytebeat!("t*5&t>>7");
+/// ```
+///
+/// The crate using the macro must depend on `bbb_core` too.
+#[proc_macro]
+pub fn bytebeat(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let source = parse_macro_input!(input as LitStr);
+    let message = match parse(&source.value()) {
+        Ok(expression) => match construct(&expression) {
+            Ok(tokens) => return tokens.into(),
+            Err(e) => e,
+        },
+        Err(e) => format!("invalid bytebeat: {}", e),
+    };
+    // `syn::Error::to_compile_error` names `::core`, which 2015 edition
+    // crates can't see
+    quote_spanned!(source.span()=> compile_error!(#message)).into()
+}
+
+fn construct(expression: &Expr) -> Result<TokenStream, String> {
+    let tokens = match *expression {
+        Expr::Time => quote!(::bbb_core::expr::Expr::Time),
+        Expr::Num(num) => {
+            let num = match num {
+                Numeral::Int(i) => {
+                    let i = Literal::i32_suffixed(i);
+                    quote!(::bbb_core::numeral::Numeral::Int(#i))
+                }
+                // a long enough digit string parses to infinity, which has
+                // no literal
+                Numeral::Float(f) if !f.is_finite() => {
+                    return Err(format!("invalid bytebeat: {} does not fit in an f32", f));
+                }
+                Numeral::Float(f) => {
+                    let f = Literal::f32_suffixed(f);
+                    quote!(::bbb_core::numeral::Numeral::Float(#f))
+                }
+            };
+            quote!(::bbb_core::expr::Expr::Num(#num))
+        }
+        Expr::UnExpr(op, ref expr) => {
+            let op = unop(op);
+            let expr = construct(expr)?;
+            quote!(::bbb_core::expr::Expr::UnExpr(#op, ::std::boxed::Box::new(#expr)))
+        }
+        Expr::BinExpr(ref expr1, op, ref expr2) => {
+            let expr1 = construct(expr1)?;
+            let op = binop(op);
+            let expr2 = construct(expr2)?;
+            quote!(::bbb_core::expr::Expr::BinExpr(
+                ::std::boxed::Box::new(#expr1),
+                #op,
+                ::std::boxed::Box::new(#expr2)
+            ))
+        }
+    };
+    Ok(tokens)
+}
+
+fn unop(op: UnOp) -> TokenStream {
+    match op {
+        UnOp::Neg => quote!(::bbb_core::ops::UnOp::Neg),
+        UnOp::BoolNot => quote!(::bbb_core::ops::UnOp::BoolNot),
+        UnOp::BitNot => quote!(::bbb_core::ops::UnOp::BitNot),
+    }
+}
+
+fn binop(op: BinOp) -> TokenStream {
+    match op {
+        BinOp::One(BinOp1::Mul) => quote!(::bbb_core::ops::BinOp::One(::bbb_core::ops::BinOp1::Mul)),
+        BinOp::One(BinOp1::Div) => quote!(::bbb_core::ops::BinOp::One(::bbb_core::ops::BinOp1::Div)),
+        BinOp::Two(BinOp2::Add) => quote!(::bbb_core::ops::BinOp::Two(::bbb_core::ops::BinOp2::Add)),
+        BinOp::Two(BinOp2::Sub) => quote!(::bbb_core::ops::BinOp::Two(::bbb_core::ops::BinOp2::Sub)),
+        BinOp::Three(BitShift::Left) => {
+            quote!(::bbb_core::ops::BinOp::Three(::bbb_core::ops::BitShift::Left))
+        }
+        BinOp::Three(BitShift::Right) => {
+            quote!(::bbb_core::ops::BinOp::Three(::bbb_core::ops::BitShift::Right))
+        }
+        BinOp::Four(_) => quote!(::bbb_core::ops::BinOp::Four(::bbb_core::ops::BitAnd)),
+        BinOp::Five(_) => quote!(::bbb_core::ops::BinOp::Five(::bbb_core::ops::BitXOr)),
+        BinOp::Six(_) => quote!(::bbb_core::ops::BinOp::Six(::bbb_core::ops::BitOr)),
+    }
+}
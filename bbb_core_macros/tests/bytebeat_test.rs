@@ -0,0 +1,30 @@
+extern crate bbb_core;
+#[macro_use]
+extern crate bbb_core_macros;
+
+use bbb_core::expr::Expr;
+use bbb_core::parser::parse;
+
+#[test]
+fn expands_to_parsed_tree_test() {
+    let expression: Expr = bytebeat!("t*5&t>>7");
+    assert_eq!(expression, parse("t*5&t>>7").unwrap());
+
+    for (built, text) in [
+        (bytebeat!("((t<<1)^((t<<1)+(t>>7)&t>>12))|t>>(4-(1^7&(t>>19)))|t>>7"),
+         "((t<<1)^((t<<1)+(t>>7)&t>>12))|t>>(4-(1^7&(t>>19)))|t>>7"),
+        (bytebeat!("-(t*-3)/~t-!t"), "-(t*-3)/~t-!t"),
+        (bytebeat!("t*1.5|-2147483647"), "t*1.5|-2147483647"),
+        (bytebeat!("t-1-2"), "t-1-2"),
+    ] {
+        assert_eq!(built, parse(text).unwrap(), "{}", text);
+    }
+}
+
+#[test]
+fn expands_to_an_expression_test() {
+    fn takes_expr(expression: Expr) -> usize {
+        expression.node_count()
+    }
+    assert_eq!(takes_expr(bytebeat!("t")), 1);
+}
@@ -0,0 +1,35 @@
+extern crate bbb_core;
+
+use std::time::Instant;
+
+use bbb_core::cache::CachedExpr;
+use bbb_core::eval::eval;
+use bbb_core::parser::parse;
+
+const SAMPLES: i32 = 8_000 * 60;
+
+fn main() {
+    let expressions = [
+        "t*(t>>12&7)",
+        "(t * 9 & t >> 4 | t * 5 & t >> 7 | t * 3 & t / 1024) - 1",
+        "((t<<1)^((t<<1)+(t>>7)&t>>12))|t>>(4-(1^7&(t>>19)))|t>>7",
+    ];
+
+    for e in expressions.iter() {
+        let expression = parse(e).unwrap();
+        let mut cached = CachedExpr::new(&expression);
+
+        let start = Instant::now();
+        let plain = (0..SAMPLES).fold(0i32, |acc, t| acc ^ eval(t, &expression).unwrap_or(0));
+        let plain_time = start.elapsed();
+
+        let start = Instant::now();
+        let fast = (0..SAMPLES).fold(0i32, |acc, t| acc ^ cached.eval(t).unwrap_or(0));
+        let cached_time = start.elapsed();
+
+        assert_eq!(plain, fast);
+        println!("{}", e);
+        println!("    eval:   {:?}", plain_time);
+        println!("    cached: {:?} ({} cached sub-expressions)", cached_time, cached.cached_count());
+    }
+}
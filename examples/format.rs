@@ -0,0 +1,49 @@
+extern crate bbb_core;
+
+use std::env;
+use std::io::{self, Read};
+use std::process;
+
+use bbb_core::formatter::{self, Check, Config};
+
+/// Formats the expression on stdin. With `--check`, prints nothing and
+/// exits with 1 if the input isn't formatted. `--width N` sets the width.
+fn main() {
+    let mut config = Config::default();
+    let mut check = false;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--check" => check = true,
+            "--width" => {
+                config.width = args.next().and_then(|n| n.parse().ok()).expect("--width needs a number")
+            }
+            _ => panic!("unknown argument {}", arg),
+        }
+    }
+
+    let mut source = String::new();
+    io::stdin().read_to_string(&mut source).unwrap();
+
+    if check {
+        match formatter::check(&source, &config) {
+            Ok(Check::Formatted) => {}
+            Ok(Check::Unformatted { line }) => {
+                eprintln!("not formatted, from line {}", line);
+                process::exit(1);
+            }
+            Err(e) => {
+                eprintln!("{}", e);
+                process::exit(2);
+            }
+        }
+    } else {
+        match formatter::format_source(&source, &config) {
+            Ok(formatted) => print!("{}", formatted),
+            Err(e) => {
+                eprintln!("{}", e);
+                process::exit(2);
+            }
+        }
+    }
+}
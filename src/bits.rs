@@ -0,0 +1,283 @@
+use eval::eval;
+use expr::Expr;
+use ops::*;
+use self::Expr::*;
+use self::BinOp::*;
+
+/// For each bit of a value, the bits of `t` it may depend on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Influence {
+    /// Bit `j` of `bits[i]` is set if bit `i` of the value may change when
+    /// bit `j` of `t` does.
+    pub bits: [u32; 32],
+    /// The bits of `t` that may decide whether evaluation fails.
+    pub failure: u32,
+}
+
+impl Influence {
+    fn none() -> Self {
+        Influence {
+            bits: [0; 32],
+            failure: 0,
+        }
+    }
+
+    /// The bits of `t` that affect the value at all.
+    pub fn any(&self) -> u32 {
+        self.bits.iter().fold(self.failure, |mask, &bit| mask | bit)
+    }
+
+    /// The bits of `t` that affect the low 8 bits of the value, which are
+    /// all that ends up in a sample.
+    pub fn sample(&self) -> u32 {
+        self.bits[..8].iter().fold(self.failure, |mask, &bit| mask | bit)
+    }
+
+    /// How fast the value changes, or `None` if it doesn't depend on `t`.
+    pub fn time_scale(&self) -> Option<TimeScale> {
+        match self.any() {
+            0 => None,
+            mask => Some(TimeScale {
+                fastest: mask.trailing_zeros(),
+                slowest: 31 - mask.leading_zeros(),
+            }),
+        }
+    }
+
+    fn all_bits(&self) -> u32 {
+        self.bits.iter().fold(0, |mask, &bit| mask | bit)
+    }
+}
+
+/// The lowest and highest bits of `t` a value depends on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TimeScale {
+    pub fastest: u32,
+    pub slowest: u32,
+}
+
+impl TimeScale {
+    /// The value holds still for at least this many samples at a time.
+    pub fn step(&self) -> u64 {
+        1 << self.fastest
+    }
+
+    /// The value repeats at least this often, as long as `t` doesn't wrap.
+    pub fn cycle(&self) -> u64 {
+        2 << self.slowest
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Method {
+    /// Worked out from the operators, so no dependency is missed, though
+    /// some may not be real.
+    Symbolic,
+    /// Observed by flipping bits of `t`, so every dependency is real, though
+    /// some may have been missed.
+    Sampled,
+}
+
+/// The influence of `t` on one sub-expression, identified by its path from
+/// the root as in `range::Report`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Part {
+    pub path: Vec<usize>,
+    pub influence: Influence,
+    pub method: Method,
+}
+
+/// Works out symbolically which bits of `t` every bit of the output of
+/// `expression` may depend on.
+///
+/// Bitwise operators and shifts by a constant move dependencies between
+/// bits exactly, and carries only move them upwards. Divisions and shifts by
+/// a varying amount make every bit depend on everything their operands do.
+pub fn influence(expression: &Expr) -> Influence {
+    symbolic(expression).0
+}
+
+/// Finds which bits of `t` influence the output of `expression` by
+/// evaluating it at `samples` pseudo-random times below `2^time_bits`, each
+/// with every one of those bits of `t` flipped in turn. Higher bits of `t`
+/// are never flipped.
+///
+/// Like rendering, this evaluates the expression, so in a debug build it
+/// panics if the expression overflows at any of those times.
+pub fn sampled_influence(expression: &Expr, time_bits: u32, samples: usize) -> Influence {
+    assert!(time_bits <= 31, "t is never negative");
+    let mask = ((1u64 << time_bits) - 1) as i32;
+    let mut influence = Influence::none();
+    let mut state = 0x9e37_79b9u32;
+
+    for _ in 0..samples {
+        // xorshift
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        let t = state as i32 & mask;
+        let value = eval(t, expression);
+
+        for j in 0..time_bits {
+            let flipped = eval(t ^ (1 << j), expression);
+            if value.is_ok() != flipped.is_ok() {
+                influence.failure |= 1 << j;
+            }
+            let changed = (value.unwrap_or(0) ^ flipped.unwrap_or(0)) as u32;
+            for (i, bit) in influence.bits.iter_mut().enumerate() {
+                if changed >> i & 1 == 1 {
+                    *bit |= 1 << j;
+                }
+            }
+        }
+    }
+    influence
+}
+
+/// Maps the influence of `t` on every sub-expression of `expression`,
+/// root first.
+///
+/// Each part is worked out symbolically where that is precise, and sampled
+/// with `sampled_influence` below `2^time_bits` where it is below a
+/// division or a shift by a varying amount.
+pub fn influence_map(expression: &Expr, time_bits: u32) -> Vec<Part> {
+    let mut parts = Vec::new();
+    let mut path = Vec::new();
+    walk(expression, time_bits, &mut path, &mut parts);
+    parts
+}
+
+const SAMPLES: usize = 256;
+
+fn walk(expression: &Expr, time_bits: u32, path: &mut Vec<usize>, parts: &mut Vec<Part>) {
+    let (influence, precise) = symbolic(expression);
+    parts.push(if precise {
+        Part {
+            path: path.clone(),
+            influence,
+            method: Method::Symbolic,
+        }
+    } else {
+        Part {
+            path: path.clone(),
+            influence: sampled_influence(expression, time_bits, SAMPLES),
+            method: Method::Sampled,
+        }
+    });
+
+    match *expression {
+        Time | Num(_) => {}
+        UnExpr(_, ref expr) => {
+            path.push(0);
+            walk(expr, time_bits, path, parts);
+            path.pop();
+        }
+        BinExpr(ref expr1, _, ref expr2) => {
+            path.push(0);
+            walk(expr1, time_bits, path, parts);
+            path.pop();
+            path.push(1);
+            walk(expr2, time_bits, path, parts);
+            path.pop();
+        }
+    }
+}
+
+/// The symbolic influence on `expression`, and whether it was found without
+/// giving up on any operator.
+fn symbolic(expression: &Expr) -> (Influence, bool) {
+    match *expression {
+        Time => {
+            let mut influence = Influence::none();
+            for (i, bit) in influence.bits.iter_mut().enumerate() {
+                *bit = 1 << i;
+            }
+            (influence, true)
+        }
+        Num(_) => (Influence::none(), true),
+        UnExpr(op, ref expr) => {
+            let (x, precise) = symbolic(expr);
+            match op {
+                UnOp::Neg => (carried(x, Influence::none()), precise),
+                UnOp::BoolNot | UnOp::BitNot => (x, precise),
+            }
+        }
+        BinExpr(ref expr1, op, ref expr2) => {
+            let (a, precise1) = symbolic(expr1);
+            let (b, precise2) = symbolic(expr2);
+            let precise = precise1 && precise2;
+            match (op, expr1.literal(), expr2.literal()) {
+                (Four(BitAnd), _, Some(mask)) => (masked(a, b, |i| mask >> i & 1 == 1), precise),
+                (Four(BitAnd), Some(mask), _) => (masked(b, a, |i| mask >> i & 1 == 1), precise),
+                (Six(BitOr), _, Some(mask)) => (masked(a, b, |i| mask >> i & 1 == 0), precise),
+                (Six(BitOr), Some(mask), _) => (masked(b, a, |i| mask >> i & 1 == 0), precise),
+                (Four(_), _, _) | (Five(_), _, _) | (Six(_), _, _) => {
+                    let mut influence = a;
+                    for (bit, &other) in influence.bits.iter_mut().zip(b.bits.iter()) {
+                        *bit |= other;
+                    }
+                    influence.failure |= b.failure;
+                    (influence, precise)
+                }
+                (Two(_), _, _) | (One(BinOp1::Mul), _, _) => (carried(a, b), precise),
+                (Three(shift), _, Some(k)) if (0..32).contains(&k) => {
+                    (shifted(a, b, shift, k as usize), precise)
+                }
+                (One(BinOp1::Div), _, _) => {
+                    let failure = a.failure | b.failure | b.all_bits();
+                    let mut influence = spread(a, b);
+                    influence.failure = failure;
+                    (influence, false)
+                }
+                (Three(_), _, _) => (spread(a, b), false),
+            }
+        }
+    }
+}
+
+/// Keeps the bits of `a` for which `keep` holds; the others are fixed by a
+/// constant `b`.
+fn masked<F: Fn(usize) -> bool>(mut a: Influence, b: Influence, keep: F) -> Influence {
+    for (i, bit) in a.bits.iter_mut().enumerate() {
+        if !keep(i) {
+            *bit = 0;
+        }
+    }
+    a.failure |= b.failure;
+    a
+}
+
+/// Addition, subtraction and multiplication: each bit of the result depends
+/// on the same and lower bits of both operands.
+fn carried(a: Influence, b: Influence) -> Influence {
+    let mut influence = Influence::none();
+    let mut below = 0;
+    for i in 0..32 {
+        below |= a.bits[i] | b.bits[i];
+        influence.bits[i] = below;
+    }
+    influence.failure = a.failure | b.failure;
+    influence
+}
+
+fn shifted(a: Influence, b: Influence, shift: BitShift, k: usize) -> Influence {
+    let mut influence = Influence::none();
+    for i in 0..32 {
+        influence.bits[i] = match shift {
+            BitShift::Right => a.bits[(i + k).min(31)],
+            BitShift::Left if i >= k => a.bits[i - k],
+            BitShift::Left => 0,
+        };
+    }
+    influence.failure = a.failure | b.failure;
+    influence
+}
+
+/// Every bit of the result may depend on every bit of both operands.
+fn spread(a: Influence, b: Influence) -> Influence {
+    let all = a.all_bits() | b.all_bits();
+    Influence {
+        bits: [all; 32],
+        failure: a.failure | b.failure,
+    }
+}
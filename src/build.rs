@@ -0,0 +1,108 @@
+use std::ops;
+
+use expr::Expr;
+use numeral::Numeral;
+use ops::*;
+
+/// An expression under construction, which the operators from `std::ops`
+/// combine into bigger ones, so that `t() * 5 & t() >> 7` builds the same
+/// tree as parsing `t*5&t>>7`.
+///
+/// Rust ranks `*`, `/`, `+`, `-`, `<<`, `>>`, `&`, `^` and `|` exactly as the
+/// parser does, but groups a chain of one level to the left where the
+/// parser groups it to the right: `t() - 1 - 2` builds `(t-1)-2`, while the
+/// text `t-1-2` parses as `t-(1-2)`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Builder(pub Expr);
+
+/// `t`.
+pub fn t() -> Builder {
+    Builder(Expr::Time)
+}
+
+/// An integer literal, built as the parser builds its text: a negative one
+/// is `-` applied to its magnitude, and `i32::MIN`, whose magnitude is too
+/// big for a literal, is `~2147483647`.
+pub fn lit(value: i32) -> Builder {
+    let magnitude = |i: i32| Box::new(Expr::Num(Numeral::Int(i)));
+    Builder(match value {
+        i32::MIN => Expr::UnExpr(UnOp::BitNot, magnitude(i32::MAX)),
+        _ if value < 0 => Expr::UnExpr(UnOp::Neg, magnitude(-value)),
+        _ => Expr::Num(Numeral::Int(value)),
+    })
+}
+
+impl Builder {
+    pub fn build(self) -> Expr {
+        self.0
+    }
+}
+
+impl From<Builder> for Expr {
+    fn from(builder: Builder) -> Expr {
+        builder.0
+    }
+}
+
+impl From<Expr> for Builder {
+    fn from(expression: Expr) -> Builder {
+        Builder(expression)
+    }
+}
+
+impl From<i32> for Builder {
+    fn from(value: i32) -> Builder {
+        lit(value)
+    }
+}
+
+fn binary(a: Builder, op: BinOp, b: Builder) -> Builder {
+    Builder(Expr::BinExpr(Box::new(a.0), op, Box::new(b.0)))
+}
+
+macro_rules! binary_op {
+    ($trait_:ident, $method:ident, $op:expr) => {
+        impl<B: Into<Builder>> ops::$trait_<B> for Builder {
+            type Output = Builder;
+
+            fn $method(self, other: B) -> Builder {
+                binary(self, $op, other.into())
+            }
+        }
+
+        impl ops::$trait_<Builder> for i32 {
+            type Output = Builder;
+
+            fn $method(self, other: Builder) -> Builder {
+                binary(lit(self), $op, other)
+            }
+        }
+    };
+}
+
+binary_op!(Mul, mul, BinOp::One(BinOp1::Mul));
+binary_op!(Div, div, BinOp::One(BinOp1::Div));
+binary_op!(Add, add, BinOp::Two(BinOp2::Add));
+binary_op!(Sub, sub, BinOp::Two(BinOp2::Sub));
+binary_op!(Shl, shl, BinOp::Three(BitShift::Left));
+binary_op!(Shr, shr, BinOp::Three(BitShift::Right));
+binary_op!(BitAnd, bitand, BinOp::Four(BitAnd));
+binary_op!(BitXor, bitxor, BinOp::Five(BitXOr));
+binary_op!(BitOr, bitor, BinOp::Six(BitOr));
+
+impl ops::Neg for Builder {
+    type Output = Builder;
+
+    fn neg(self) -> Builder {
+        Builder(Expr::UnExpr(UnOp::Neg, Box::new(self.0)))
+    }
+}
+
+/// Rust's `!` is bitwise on integers, so it builds `~`.
+impl ops::Not for Builder {
+    type Output = Builder;
+
+    fn not(self) -> Builder {
+        Builder(Expr::UnExpr(UnOp::BitNot, Box::new(self.0)))
+    }
+}
@@ -0,0 +1,253 @@
+use std::cmp;
+
+use eval::{eval, eval_binop, eval_binop_block, eval_unop, eval_unop_block};
+use expr::Expr;
+use ops::*;
+use self::Expr::*;
+
+/// How often the value of an expression can change as `t` counts up.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Rate {
+    /// The value never changes.
+    Constant,
+    /// The value depends on `t` only through `t >> k`, so it changes at most
+    /// once every `2^k` samples. `Shift(0)` changes every sample.
+    Shift(u32),
+}
+
+impl Rate {
+    fn min(self, other: Rate) -> Rate {
+        match (self, other) {
+            (Rate::Constant, rate) | (rate, Rate::Constant) => rate,
+            (Rate::Shift(a), Rate::Shift(b)) => Rate::Shift(cmp::min(a, b)),
+        }
+    }
+
+    fn is_slow(self) -> bool {
+        self != Rate::Shift(0)
+    }
+
+    fn key(self, time: i32) -> i32 {
+        match self {
+            Rate::Constant => 0,
+            Rate::Shift(k) => time >> k,
+        }
+    }
+}
+
+pub fn rate(expression: &Expr) -> Rate {
+    match *expression {
+        Time => Rate::Shift(0),
+        Num(_) => Rate::Constant,
+        UnExpr(_, ref expr) => rate(expr),
+        BinExpr(ref expr1, op, ref expr2) => {
+            if let Some(k) = selected_bits(expr1, op, expr2) {
+                return k;
+            }
+            rate(expr1).min(rate(expr2))
+        }
+    }
+}
+
+/// Recognises `t >> k` and `t & mask`, which only look at the high bits of
+/// `t` even though `t` itself changes every sample.
+fn selected_bits(expr1: &Expr, op: BinOp, expr2: &Expr) -> Option<Rate> {
+    match (expr1, op, expr2.literal()) {
+        (&Time, BinOp::Three(BitShift::Right), Some(k)) if (0..32).contains(&k) => {
+            Some(Rate::Shift(k as u32))
+        }
+        (&Time, BinOp::Four(BitAnd), Some(mask)) => Some(mask_rate(mask)),
+        _ => match (expr1.literal(), op, expr2) {
+            (Some(mask), BinOp::Four(BitAnd), &Time) => Some(mask_rate(mask)),
+            _ => None,
+        },
+    }
+}
+
+fn mask_rate(mask: i32) -> Rate {
+    if mask == 0 {
+        Rate::Constant
+    } else {
+        Rate::Shift(mask.trailing_zeros())
+    }
+}
+
+/// An evaluator that remembers the value of every slow-varying
+/// sub-expression and only recomputes it when the bits of `t` it depends on
+/// change. It gives the same results as `eval`.
+///
+/// `eval::EvalContext` renders blocks with one, so `ExprSignal::fill`
+/// caches too.
+#[derive(Clone, Debug)]
+pub struct CachedExpr {
+    root: Node,
+    cached: usize,
+}
+
+#[derive(Clone, Debug)]
+enum Node {
+    Time,
+    Num(i32),
+    Unary(UnOp, Box<Node>),
+    Binary(Box<Node>, BinOp, Box<Node>),
+    Cached(Box<Slot>),
+}
+
+#[derive(Clone, Debug)]
+struct Slot {
+    rate: Rate,
+    expression: Expr,
+    last: Option<(i32, Result<i32, &'static str>)>,
+}
+
+impl CachedExpr {
+    pub fn new(expression: &Expr) -> Self {
+        let (root, _, cached) = build(expression);
+        CachedExpr { root, cached }
+    }
+
+    /// The number of sub-expressions whose values are cached.
+    pub fn cached_count(&self) -> usize {
+        self.cached
+    }
+
+    pub fn eval(&mut self, time: i32) -> Result<i32, &'static str> {
+        eval_node(&mut self.root, time)
+    }
+
+    /// Evaluates a block as `eval::eval_block` does, leaving the samples
+    /// that fail marked in `failed`. A cached sub-expression is computed
+    /// once for each run of samples in which its inputs don't change.
+    pub(crate) fn eval_block(
+        &mut self,
+        t_start: i32,
+        out: &mut [i32],
+        failed: &mut [bool],
+        scratch: &mut Vec<Vec<i32>>,
+    ) {
+        eval_node_block(&mut self.root, t_start, out, failed, scratch);
+    }
+}
+
+/// Builds the node for `expression`, with its rate and the number of
+/// cached nodes in it.
+fn build(expression: &Expr) -> (Node, Rate, usize) {
+    let (node, rate, cached) = match *expression {
+        Time => return (Node::Time, Rate::Shift(0), 0),
+        Num(_) => return (Node::Num(expression.literal().unwrap()), Rate::Constant, 0),
+        UnExpr(op, ref expr) => {
+            let (node, rate, cached) = build(expr);
+            (Node::Unary(op, Box::new(node)), rate, cached)
+        }
+        BinExpr(ref expr1, op, ref expr2) => {
+            let (node1, rate1, cached1) = build(expr1);
+            let (node2, rate2, cached2) = build(expr2);
+            let rate = selected_bits(expr1, op, expr2).unwrap_or_else(|| rate1.min(rate2));
+            (Node::Binary(Box::new(node1), op, Box::new(node2)), rate, cached1 + cached2)
+        }
+    };
+
+    if !rate.is_slow() {
+        return (node, rate, cached);
+    }
+
+    // the slots the children created are replaced by this one
+    let slot = Slot {
+        rate,
+        expression: expression.clone(),
+        last: None,
+    };
+    (Node::Cached(Box::new(slot)), rate, 1)
+}
+
+impl Slot {
+    /// The value at `time`, computed only if the bits of `t` it depends on
+    /// changed since the last time.
+    fn value(&mut self, time: i32) -> Result<i32, &'static str> {
+        let key = self.rate.key(time);
+        match self.last {
+            Some((last_key, value)) if last_key == key => value,
+            _ => {
+                let value = eval(time, &self.expression);
+                self.last = Some((key, value));
+                value
+            }
+        }
+    }
+
+    fn eval_block(&mut self, t_start: i32, out: &mut [i32], failed: &mut [bool]) {
+        let time = |i: usize| t_start.wrapping_add(i as i32);
+        let mut start = 0;
+        while start < out.len() {
+            let key = self.rate.key(time(start));
+            let end = (start + 1..out.len())
+                .find(|&i| self.rate.key(time(i)) != key)
+                .unwrap_or(out.len());
+
+            // like `eval`, failed samples don't get this far, and every
+            // sample of the run has the same value
+            if let Some(first) = (start..end).find(|&i| !failed[i]) {
+                let value = self.value(time(first));
+                for (x, failed) in out[start..end].iter_mut().zip(failed[start..end].iter_mut()) {
+                    match value {
+                        _ if *failed => {}
+                        Ok(value) => *x = value,
+                        Err(_) => *failed = true,
+                    }
+                }
+            }
+            start = end;
+        }
+    }
+}
+
+fn eval_node(node: &mut Node, time: i32) -> Result<i32, &'static str> {
+    match *node {
+        Node::Time => Ok(time),
+        Node::Num(i) => Ok(i),
+        Node::Unary(ref op, ref mut node) => eval_node(node, time).and_then(|x| eval_unop(op, x)),
+        Node::Binary(ref mut node1, ref op, ref mut node2) => {
+            eval_node(node1, time).and_then(|a| {
+                eval_node(node2, time).and_then(|b| eval_binop(op, a, b))
+            })
+        }
+        Node::Cached(ref mut slot) => slot.value(time),
+    }
+}
+
+fn eval_node_block(
+    node: &mut Node,
+    t_start: i32,
+    out: &mut [i32],
+    failed: &mut [bool],
+    scratch: &mut Vec<Vec<i32>>,
+) {
+    match *node {
+        Node::Time => {
+            for (i, x) in out.iter_mut().enumerate() {
+                *x = t_start.wrapping_add(i as i32);
+            }
+        }
+        Node::Num(i) => {
+            for x in out.iter_mut() {
+                *x = i;
+            }
+        }
+        Node::Unary(op, ref mut node) => {
+            eval_node_block(node, t_start, out, failed, scratch);
+            eval_unop_block(op, out, failed);
+        }
+        Node::Binary(ref mut node1, op, ref mut node2) => {
+            let mut rhs = scratch.pop().unwrap_or_default();
+            rhs.clear();
+            rhs.resize(out.len(), 0);
+
+            eval_node_block(node1, t_start, out, failed, scratch);
+            eval_node_block(node2, t_start, &mut rhs, failed, scratch);
+            eval_binop_block(op, out, &rhs, failed);
+
+            scratch.push(rhs);
+        }
+        Node::Cached(ref mut slot) => slot.eval_block(t_start, out, failed),
+    }
+}
@@ -0,0 +1,216 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+use expr::Expr;
+use numeral::Numeral::*;
+use ops::*;
+use self::Expr::*;
+use self::BinOp::*;
+
+/// An expression in canonical form, which unlike `Expr` can be compared
+/// with `Eq` and used as a `HashMap` key.
+#[derive(Clone, Debug)]
+pub struct Canonical {
+    expression: Expr,
+    text: String,
+}
+
+impl From<&Expr> for Canonical {
+    fn from(expression: &Expr) -> Canonical {
+        let expression = canonicalize(expression);
+        let mut text = String::new();
+        write_canonical(&expression, &mut text);
+        Canonical { expression, text }
+    }
+}
+
+// a canonical expression has no float literals, and its text spells out
+// the whole tree, so comparing texts is comparing expressions
+impl PartialEq for Canonical {
+    fn eq(&self, other: &Canonical) -> bool {
+        self.text == other.text
+    }
+}
+
+impl Eq for Canonical {}
+
+impl Hash for Canonical {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.text.hash(state);
+    }
+}
+
+impl Canonical {
+    pub fn expression(&self) -> &Expr {
+        &self.expression
+    }
+
+    /// The canonical text, which parses back to `expression()`.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// A hash of the canonical text that is the same in every process and
+    /// every version of this crate, for use as a persistent key.
+    pub fn content_hash(&self) -> u64 {
+        fnv1a(self.text.as_bytes())
+    }
+}
+
+impl fmt::Display for Canonical {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+/// Rewrites `expression` so that all expressions which only differ in how
+/// their literals are written, in the order of the operands of `*`, `+`,
+/// `&`, `^` and `|`, or in how chains of one of those operators are
+/// grouped, come out the same.
+///
+/// Literals become integers, truncated as `eval` does. A negative one
+/// becomes the negation of its magnitude, as the parser reads `-1`, and
+/// `i32::MIN` becomes `~2147483647`. Operand chains are sorted and grouped
+/// to the right, as the parser groups them.
+pub fn canonicalize(expression: &Expr) -> Expr {
+    match *expression {
+        Time => Time,
+        Num(Int(i)) => canonical_literal(i),
+        Num(Float(f)) => canonical_literal(f as i32),
+        UnExpr(op, ref expr) => UnExpr(op, Box::new(canonicalize(expr))),
+        BinExpr(ref expr1, op, ref expr2) => {
+            if !is_commutative(op) {
+                return BinExpr(Box::new(canonicalize(expr1)), op, Box::new(canonicalize(expr2)));
+            }
+
+            let mut operands = Vec::new();
+            chain(expression, op, &mut operands);
+            let mut operands: Vec<Expr> = operands.into_iter().map(canonicalize).collect();
+            operands.sort_by(order);
+
+            let last = operands.pop().expect("a chain has two operands");
+            operands
+                .into_iter()
+                .rev()
+                .fold(last, |right, left| BinExpr(Box::new(left), op, Box::new(right)))
+        }
+    }
+}
+
+fn canonical_literal(i: i32) -> Expr {
+    match i {
+        i32::MIN => UnExpr(UnOp::BitNot, Box::new(Num(Int(i32::MAX)))),
+        i if i < 0 => UnExpr(UnOp::Neg, Box::new(Num(Int(-i)))),
+        i => Num(Int(i)),
+    }
+}
+
+/// The canonical text of `expression`, without spaces and with parentheses
+/// around every operand that is itself an operation.
+pub fn canonical_text(expression: &Expr) -> String {
+    Canonical::from(expression).text
+}
+
+/// `Canonical::content_hash` of `expression`.
+pub fn content_hash(expression: &Expr) -> u64 {
+    Canonical::from(expression).content_hash()
+}
+
+pub(crate) fn is_commutative(op: BinOp) -> bool {
+    match op {
+        One(BinOp1::Mul) | Two(BinOp2::Add) | Four(_) | Five(_) | Six(_) => true,
+        One(BinOp1::Div) | Two(BinOp2::Sub) | Three(_) => false,
+    }
+}
+
+/// Collects the operands of a chain of `op`, however it is grouped.
+pub(crate) fn chain<'a>(expression: &'a Expr, op: BinOp, operands: &mut Vec<&'a Expr>) {
+    match *expression {
+        BinExpr(ref expr1, o, ref expr2) if o == op => {
+            chain(expr1, op, operands);
+            chain(expr2, op, operands);
+        }
+        _ => operands.push(expression),
+    }
+}
+
+/// An arbitrary but fixed order on canonical expressions.
+fn order(a: &Expr, b: &Expr) -> Ordering {
+    match (a, b) {
+        (&Num(Int(x)), &Num(Int(y))) => x.cmp(&y),
+        (&UnExpr(op1, ref x), &UnExpr(op2, ref y)) => {
+            (op1 as u8).cmp(&(op2 as u8)).then_with(|| order(x, y))
+        }
+        (&BinExpr(ref a1, op1, ref b1), &BinExpr(ref a2, op2, ref b2)) => binop_rank(op1)
+            .cmp(&binop_rank(op2))
+            .then_with(|| order(a1, a2))
+            .then_with(|| order(b1, b2)),
+        _ => rank(a).cmp(&rank(b)),
+    }
+}
+
+fn rank(expression: &Expr) -> u8 {
+    match *expression {
+        Time => 0,
+        Num(_) => 1,
+        UnExpr(..) => 2,
+        BinExpr(..) => 3,
+    }
+}
+
+fn binop_rank(op: BinOp) -> u8 {
+    match op {
+        One(BinOp1::Mul) => 0,
+        One(BinOp1::Div) => 1,
+        Two(BinOp2::Add) => 2,
+        Two(BinOp2::Sub) => 3,
+        Three(BitShift::Left) => 4,
+        Three(BitShift::Right) => 5,
+        Four(_) => 6,
+        Five(_) => 7,
+        Six(_) => 8,
+    }
+}
+
+/// Writes `expression` as it is, without canonicalizing it first.
+fn write_canonical(expression: &Expr, out: &mut String) {
+    match *expression {
+        Time => out.push('t'),
+        Num(Int(i)) => out.push_str(&i.to_string()),
+        Num(Float(f)) => out.push_str(&(f as i32).to_string()),
+        UnExpr(op, ref expr) => {
+            out.push_str(op.symbol());
+            // `-1` is written as it reads, without parentheses
+            match (op, &**expr) {
+                (_, &Time) | (UnOp::Neg, &Num(_)) => write_canonical(expr, out),
+                _ => write_grouped(expr, out),
+            }
+        }
+        BinExpr(ref expr1, op, ref expr2) => {
+            write_operand(expr1, out);
+            out.push_str(op.symbol());
+            write_operand(expr2, out);
+        }
+    }
+}
+
+fn write_operand(expression: &Expr, out: &mut String) {
+    match *expression {
+        BinExpr(..) => write_grouped(expression, out),
+        _ => write_canonical(expression, out),
+    }
+}
+
+fn write_grouped(expression: &Expr, out: &mut String) {
+    out.push('(');
+    write_canonical(expression, out);
+    out.push(')');
+}
+
+/// 64-bit FNV-1a, which unlike `DefaultHasher` is fixed for good.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
@@ -0,0 +1,183 @@
+use std::collections::HashMap;
+
+use eval::{eval_binop, eval_unop};
+use expr::Expr;
+use numeral::Numeral;
+use ops::*;
+
+/// Index of a node in an `ExprDag`.
+pub type NodeId = usize;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Node {
+    Time,
+    Num(Numeral),
+    UnExpr(UnOp, NodeId),
+    BinExpr(NodeId, BinOp, NodeId),
+}
+
+/// `Numeral` can't be hashed because of its float, so literals are interned
+/// by their bits.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum Key {
+    Time,
+    Int(i32),
+    Float(u32),
+    UnExpr(UnOp, NodeId),
+    BinExpr(NodeId, BinOp, NodeId),
+}
+
+/// An expression in which structurally equal sub-expressions share a
+/// single node.
+///
+/// Nodes are stored children first, so every node's operands have smaller
+/// ids than the node itself and the last node is the root.
+#[derive(Clone, Debug)]
+pub struct ExprDag {
+    nodes: Vec<Node>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DagStats {
+    /// Nodes in the expression tree, counting every repeat.
+    pub tree_nodes: usize,
+    /// Nodes left after deduplication.
+    pub dag_nodes: usize,
+}
+
+impl From<&Expr> for ExprDag {
+    fn from(expression: &Expr) -> ExprDag {
+        let mut nodes = Vec::new();
+        intern(expression, &mut nodes, &mut HashMap::new());
+        ExprDag { nodes }
+    }
+}
+
+fn intern(expression: &Expr, nodes: &mut Vec<Node>, ids: &mut HashMap<Key, NodeId>) -> NodeId {
+    let (key, node) = match *expression {
+        Expr::Time => (Key::Time, Node::Time),
+        Expr::Num(Numeral::Int(i)) => (Key::Int(i), Node::Num(Numeral::Int(i))),
+        Expr::Num(Numeral::Float(f)) => (Key::Float(f.to_bits()), Node::Num(Numeral::Float(f))),
+        Expr::UnExpr(op, ref expr) => {
+            let id = intern(expr, nodes, ids);
+            (Key::UnExpr(op, id), Node::UnExpr(op, id))
+        }
+        Expr::BinExpr(ref expr1, op, ref expr2) => {
+            let id1 = intern(expr1, nodes, ids);
+            let id2 = intern(expr2, nodes, ids);
+            (Key::BinExpr(id1, op, id2), Node::BinExpr(id1, op, id2))
+        }
+    };
+
+    *ids.entry(key).or_insert_with(|| {
+        nodes.push(node);
+        nodes.len() - 1
+    })
+}
+
+impl ExprDag {
+    pub fn nodes(&self) -> &[Node] {
+        &self.nodes
+    }
+
+    pub fn root(&self) -> NodeId {
+        self.nodes.len() - 1
+    }
+
+    pub fn to_expr(&self) -> Expr {
+        self.expand(self.root())
+    }
+
+    fn expand(&self, id: NodeId) -> Expr {
+        match self.nodes[id] {
+            Node::Time => Expr::Time,
+            Node::Num(n) => Expr::Num(n),
+            Node::UnExpr(op, id) => Expr::UnExpr(op, Box::new(self.expand(id))),
+            Node::BinExpr(id1, op, id2) => {
+                Expr::BinExpr(Box::new(self.expand(id1)), op, Box::new(self.expand(id2)))
+            }
+        }
+    }
+
+    pub fn stats(&self) -> DagStats {
+        let mut sizes: Vec<usize> = Vec::with_capacity(self.nodes.len());
+        for node in &self.nodes {
+            let size = match *node {
+                Node::Time | Node::Num(_) => 1,
+                Node::UnExpr(_, id) => sizes[id].saturating_add(1),
+                Node::BinExpr(id1, _, id2) => sizes[id1].saturating_add(sizes[id2]).saturating_add(1),
+            };
+            sizes.push(size);
+        }
+
+        DagStats {
+            tree_nodes: sizes[self.root()],
+            dag_nodes: self.nodes.len(),
+        }
+    }
+}
+
+/// Evaluates an `ExprDag`, computing each shared node at most once per
+/// sample. Nodes are visited in the same order `eval` visits the tree, so
+/// the results are the same.
+#[derive(Clone, Debug)]
+pub struct DagEval {
+    dag: ExprDag,
+    values: Vec<Result<i32, &'static str>>,
+    stamps: Vec<u32>,
+    stamp: u32,
+}
+
+impl From<ExprDag> for DagEval {
+    fn from(dag: ExprDag) -> DagEval {
+        let len = dag.nodes.len();
+        DagEval {
+            dag,
+            values: vec![Ok(0); len],
+            stamps: vec![0; len],
+            stamp: 0,
+        }
+    }
+}
+
+impl DagEval {
+    pub fn dag(&self) -> &ExprDag {
+        &self.dag
+    }
+
+    pub fn eval(&mut self, time: i32) -> Result<i32, &'static str> {
+        self.stamp = self.stamp.wrapping_add(1);
+        if self.stamp == 0 {
+            // every stale stamp would look current after wrapping around
+            for stamp in self.stamps.iter_mut() {
+                *stamp = 0;
+            }
+            self.stamp = 1;
+        }
+
+        let root = self.dag.root();
+        self.eval_node(root, time)
+    }
+
+    fn eval_node(&mut self, id: NodeId, time: i32) -> Result<i32, &'static str> {
+        if self.stamps[id] == self.stamp {
+            return self.values[id];
+        }
+
+        let value = match self.dag.nodes[id] {
+            Node::Time => Ok(time),
+            Node::Num(Numeral::Int(i)) => Ok(i),
+            Node::Num(Numeral::Float(f)) => Ok(f as i32),
+            Node::UnExpr(op, id) => self.eval_node(id, time).and_then(|x| eval_unop(&op, x)),
+            Node::BinExpr(id1, op, id2) => {
+                self.eval_node(id1, time).and_then(|a| {
+                    self.eval_node(id2, time).and_then(|b| eval_binop(&op, a, b))
+                })
+            }
+        };
+
+        self.values[id] = value;
+        self.stamps[id] = self.stamp;
+        value
+    }
+}
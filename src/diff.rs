@@ -0,0 +1,260 @@
+use expr::Expr;
+use printer::{groups_left, groups_right, groups_unary_operand, print, Style};
+use self::Expr::*;
+
+/// One change between two expressions. Paths are taken from the root as in
+/// `range::Report`, in the old expression for what was there before and in
+/// the new one for what is there after.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Edit {
+    /// The sub-expression at `old_path` became `new`, at `new_path`.
+    Replace {
+        old_path: Vec<usize>,
+        new_path: Vec<usize>,
+        old: Expr,
+        new: Expr,
+    },
+    /// An operator was wrapped around an existing sub-expression, which is
+    /// now its operand `kept`. `new` is the new sub-expression at `path`.
+    Insert { path: Vec<usize>, new: Expr, kept: usize },
+    /// The operator at `path` was removed and its operand `kept` took its
+    /// place. `old` is the sub-expression that was at `path`.
+    Delete { path: Vec<usize>, old: Expr, kept: usize },
+}
+
+impl Edit {
+    /// Describes the edit in words, quoting the operator around it in the
+    /// old expression `old_root` where that helps.
+    pub fn describe(&self, old_root: &Expr) -> String {
+        match *self {
+            Edit::Replace { ref old_path, ref old, ref new, .. } => {
+                let what = match (old, new) {
+                    (&Num(_), &Num(_)) => "constant",
+                    (&BinExpr(ref a1, op1, ref b1), &BinExpr(ref a2, op2, ref b2))
+                        if a1 == a2 && b1 == b2 =>
+                    {
+                        return format!(
+                            "changed operator {}\u{2192}{} in `{}`",
+                            op1.symbol(),
+                            op2.symbol(),
+                            text(old)
+                        )
+                    }
+                    _ => "",
+                };
+                let change = if what.is_empty() {
+                    format!("replaced `{}` with `{}`", text(old), text(new))
+                } else {
+                    format!("changed {} {}\u{2192}{}", what, text(old), text(new))
+                };
+                match parent(old_root, old_path) {
+                    Some(parent) => format!("{} in `{}`", change, text(parent)),
+                    None => change,
+                }
+            }
+            Edit::Insert { ref new, .. } => format!("wrapped in `{}`", text(new)),
+            Edit::Delete { ref old, kept, .. } => {
+                format!("unwrapped `{}` from `{}`", text(operand(old, kept)), text(old))
+            }
+        }
+    }
+}
+
+/// Finds the edits that turn `old` into `new`.
+///
+/// Matching operators are compared operand by operand. A sub-expression
+/// that turns up unchanged as an operand of a new operator, or that an
+/// operator was removed from, becomes an insert or a delete. Anything else
+/// that changed is replaced as a whole.
+pub fn diff(old: &Expr, new: &Expr) -> Vec<Edit> {
+    let mut edits = Vec::new();
+    diff_at(old, new, &mut Vec::new(), &mut Vec::new(), &mut edits);
+    edits
+}
+
+fn diff_at(
+    old: &Expr,
+    new: &Expr,
+    old_path: &mut Vec<usize>,
+    new_path: &mut Vec<usize>,
+    edits: &mut Vec<Edit>,
+) {
+    if old == new {
+        return;
+    }
+
+    match (old, new) {
+        (&UnExpr(op1, ref x), &UnExpr(op2, ref y)) if op1 == op2 => {
+            descend(x, y, 0, old_path, new_path, edits);
+            return;
+        }
+        (&BinExpr(ref a1, op1, ref b1), &BinExpr(ref a2, op2, ref b2)) if op1 == op2 => {
+            descend(a1, a2, 0, old_path, new_path, edits);
+            descend(b1, b2, 1, old_path, new_path, edits);
+            return;
+        }
+        _ => {}
+    }
+
+    if let Some(kept) = new.children().position(|operand| operand == old) {
+        edits.push(Edit::Insert {
+            path: new_path.clone(),
+            new: new.clone(),
+            kept,
+        });
+    } else if let Some(kept) = old.children().position(|operand| operand == new) {
+        edits.push(Edit::Delete {
+            path: old_path.clone(),
+            old: old.clone(),
+            kept,
+        });
+    } else {
+        edits.push(Edit::Replace {
+            old_path: old_path.clone(),
+            new_path: new_path.clone(),
+            old: old.clone(),
+            new: new.clone(),
+        });
+    }
+}
+
+fn descend(
+    old: &Expr,
+    new: &Expr,
+    index: usize,
+    old_path: &mut Vec<usize>,
+    new_path: &mut Vec<usize>,
+    edits: &mut Vec<Edit>,
+) {
+    old_path.push(index);
+    new_path.push(index);
+    diff_at(old, new, old_path, new_path, edits);
+    old_path.pop();
+    new_path.pop();
+}
+
+fn operand(expression: &Expr, index: usize) -> &Expr {
+    expression.children().nth(index).expect("no such operand")
+}
+
+fn parent<'a>(root: &'a Expr, path: &[usize]) -> Option<&'a Expr> {
+    match path.split_last() {
+        Some((_, init)) => Some(init.iter().fold(root, |node, &index| operand(node, index))),
+        None => None,
+    }
+}
+
+fn text(expression: &Expr) -> String {
+    print(expression, Style::Compact)
+}
+
+const RED: &str = "\x1b[31m";
+const GREEN: &str = "\x1b[32m";
+const RESET: &str = "\x1b[0m";
+
+/// Renders the edits from `diff(old, new)` as two lines of text coloured
+/// for a terminal: the old expression with what was removed or replaced in
+/// red, then the new one with what was added or replaced in green.
+pub fn render(old: &Expr, new: &Expr, edits: &[Edit]) -> String {
+    let mut old_marks = Vec::new();
+    let mut new_marks = Vec::new();
+    for edit in edits {
+        match *edit {
+            Edit::Replace { ref old_path, ref new_path, .. } => {
+                old_marks.push(Mark { path: old_path, kept: None });
+                new_marks.push(Mark { path: new_path, kept: None });
+            }
+            Edit::Insert { ref path, kept, .. } => new_marks.push(Mark { path, kept: Some(kept) }),
+            Edit::Delete { ref path, kept, .. } => old_marks.push(Mark { path, kept: Some(kept) }),
+        }
+    }
+
+    let mut out = String::from("- ");
+    Marker::new(&old_marks, RED, &mut out).write_all(old);
+    out.push_str("\n+ ");
+    Marker::new(&new_marks, GREEN, &mut out).write_all(new);
+    out.push('\n');
+    out
+}
+
+struct Mark<'a> {
+    path: &'a [usize],
+    /// An operand of the marked node that is left uncoloured.
+    kept: Option<usize>,
+}
+
+/// Writes an expression as the printer does in the compact style,
+/// colouring the marked sub-expressions. The colour is switched only where
+/// it changes between two pieces of text, so nothing empty is coloured.
+struct Marker<'a> {
+    marks: &'a [Mark<'a>],
+    colour: &'static str,
+    path: Vec<usize>,
+    /// Whether what is written now is coloured.
+    coloured: bool,
+    /// Whether the colour is switched on in `out`.
+    on: bool,
+    out: &'a mut String,
+}
+
+impl<'a> Marker<'a> {
+    fn new(marks: &'a [Mark<'a>], colour: &'static str, out: &'a mut String) -> Marker<'a> {
+        Marker { marks, colour, path: Vec::new(), coloured: false, on: false, out }
+    }
+
+    fn write_all(mut self, expression: &Expr) {
+        self.write(expression);
+        if self.on {
+            self.out.push_str(RESET);
+        }
+    }
+
+    fn write(&mut self, expression: &Expr) {
+        let mark = self.marks.iter().find(|mark| mark.path == &self.path[..]);
+        let kept = mark.and_then(|mark| mark.kept);
+        let outer = self.coloured;
+        self.coloured |= mark.is_some();
+
+        match *expression {
+            Time | Num(_) => self.push(&print(expression, Style::Compact)),
+            UnExpr(op, ref expr) => {
+                self.push(op.symbol());
+                self.operand(expr, 0, groups_unary_operand(expr), kept);
+            }
+            BinExpr(ref expr1, op, ref expr2) => {
+                self.operand(expr1, 0, groups_left(op, expr1), kept);
+                self.push(op.symbol());
+                self.operand(expr2, 1, groups_right(op, expr2), kept);
+            }
+        }
+
+        self.coloured = outer;
+    }
+
+    fn operand(&mut self, expression: &Expr, index: usize, grouped: bool, kept: Option<usize>) {
+        if grouped {
+            self.push("(");
+        }
+        self.path.push(index);
+        if kept == Some(index) {
+            let outer = self.coloured;
+            self.coloured = false;
+            self.write(expression);
+            self.coloured = outer;
+        } else {
+            self.write(expression);
+        }
+        self.path.pop();
+        if grouped {
+            self.push(")");
+        }
+    }
+
+    fn push(&mut self, text: &str) {
+        if self.coloured != self.on {
+            self.out.push_str(if self.coloured { self.colour } else { RESET });
+            self.on = self.coloured;
+        }
+        self.out.push_str(text);
+    }
+}
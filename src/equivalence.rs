@@ -0,0 +1,117 @@
+use canonical::canonicalize;
+use eval::eval_wrapping;
+use expr::Expr;
+use optimize::optimize;
+use range::Interval;
+
+/// How hard to look for a difference between two expressions.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Checker {
+    /// Every `t` in this range is evaluated.
+    pub exhaustive: Interval,
+    /// This many pseudo-random `t` in `sampled` are evaluated after that.
+    pub samples: usize,
+    pub sampled: Interval,
+}
+
+impl Default for Checker {
+    fn default() -> Self {
+        Checker {
+            exhaustive: Interval::new(0, (1 << 16) - 1),
+            samples: 1 << 12,
+            sampled: Interval::new(0, (1 << 24) - 1),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Verdict {
+    /// The expressions have the same canonical form, so they are equal at
+    /// every `t`.
+    Identical,
+    /// The expressions played the same at this many values of `t`.
+    EquivalentUpTo(usize),
+    /// The expressions play differently at `time`: the low 8 bits of their
+    /// values, the sample a player outputs, differ, counting a failure as
+    /// the 0 it plays as. `left` and `right` are the full values.
+    Counterexample {
+        time: i32,
+        left: Result<i32, &'static str>,
+        right: Result<i32, &'static str>,
+    },
+}
+
+impl Checker {
+    /// Compares the canonical forms of `a` and `b`, then their outputs over
+    /// the exhaustive range, then at random samples, stopping at the first
+    /// level that settles the question.
+    pub fn check(&self, a: &Expr, b: &Expr) -> Verdict {
+        if syntactically_equal(a, b) {
+            return Verdict::Identical;
+        }
+
+        let exhaustive = check_exhaustive(a, b, self.exhaustive);
+        let checked = match exhaustive {
+            Verdict::EquivalentUpTo(n) => n,
+            _ => return exhaustive,
+        };
+
+        match check_sampled(a, b, self.sampled, self.samples) {
+            Verdict::EquivalentUpTo(n) => Verdict::EquivalentUpTo(checked + n),
+            verdict => verdict,
+        }
+    }
+}
+
+/// Whether `a` and `b` have the same canonical form once constants are
+/// folded and identities stripped.
+pub fn syntactically_equal(a: &Expr, b: &Expr) -> bool {
+    canonicalize(&optimize(a)) == canonicalize(&optimize(b))
+}
+
+/// Evaluates `a` and `b` at every `t` in `time`.
+pub fn check_exhaustive(a: &Expr, b: &Expr, time: Interval) -> Verdict {
+    let mut checked = 0;
+    for t in time.lo..=time.hi {
+        if let Some(counterexample) = compare(a, b, t) {
+            return counterexample;
+        }
+        checked += 1;
+    }
+    Verdict::EquivalentUpTo(checked)
+}
+
+/// Evaluates `a` and `b` at `samples` pseudo-random `t` in `time`. The same
+/// arguments always pick the same `t`.
+pub fn check_sampled(a: &Expr, b: &Expr, time: Interval, samples: usize) -> Verdict {
+    let width = (i64::from(time.hi) - i64::from(time.lo) + 1) as u64;
+    let mut state = 0x2545_f491_4f6c_dd1du64;
+    for _ in 0..samples {
+        // xorshift
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        let t = (i64::from(time.lo) + (state % width) as i64) as i32;
+        if let Some(counterexample) = compare(a, b, t) {
+            return counterexample;
+        }
+    }
+    Verdict::EquivalentUpTo(samples)
+}
+
+/// Evaluates as a player does: arithmetic wraps whatever the build, every
+/// failure plays as 0, whatever the reason, and only the sample `x as i8`
+/// is heard.
+fn compare(a: &Expr, b: &Expr, t: i32) -> Option<Verdict> {
+    let left = eval_wrapping(t, a);
+    let right = eval_wrapping(t, b);
+    if left.unwrap_or(0) as i8 == right.unwrap_or(0) as i8 {
+        None
+    } else {
+        Some(Verdict::Counterexample {
+            time: t,
+            left,
+            right,
+        })
+    }
+}
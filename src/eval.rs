@@ -1,3 +1,4 @@
+use cache::CachedExpr;
 use expr::Expr;
 use numeral::Numeral::*;
 use ops::*;
@@ -5,44 +6,320 @@ use self::Expr::*;
 use self::UnOp::*;
 use self::BinOp::*;
 
+/// Below this depth `eval` recurses; deeper sub-trees are evaluated with an
+/// explicit stack so that no tree can overflow the call stack.
+const RECURSION_LIMIT: usize = 256;
+
 pub fn eval(time: i32, expression: &Expr) -> Result<i32, &'static str> {
-    match expression {
-        &Time => Ok(time),
-        &Num(Int(i)) => Ok(i),
-        &Num(Float(f)) => Ok(f as i32),
-        &UnExpr(ref op, ref expr) => eval(time, expr.as_ref()).and_then(|x| eval_unop(op, x)),
-        &BinExpr(ref expr1, ref op, ref expr2) => {
-            eval(time, expr1.as_ref()).and_then(|a| {
-                eval(time, expr2.as_ref()).and_then(|b| eval_binop(op, a, b))
+    eval_recursive(time, expression, 0, PLAIN)
+}
+
+/// Like `eval`, but arithmetic wraps as it does in a release build whatever
+/// the build, and `i32::MIN / -1`, which panics in either, fails instead.
+pub(crate) fn eval_wrapping(time: i32, expression: &Expr) -> Result<i32, &'static str> {
+    eval_recursive(time, expression, 0, WRAPPING)
+}
+
+/// How operators are applied to values.
+#[derive(Clone, Copy)]
+struct Arithmetic {
+    unop: fn(&UnOp, i32) -> Result<i32, &'static str>,
+    binop: fn(&BinOp, i32, i32) -> Result<i32, &'static str>,
+}
+
+const PLAIN: Arithmetic = Arithmetic {
+    unop: eval_unop,
+    binop: eval_binop,
+};
+
+const WRAPPING: Arithmetic = Arithmetic {
+    unop: wrapping_unop,
+    binop: wrapping_binop,
+};
+
+fn eval_recursive(
+    time: i32,
+    expression: &Expr,
+    depth: usize,
+    arithmetic: Arithmetic,
+) -> Result<i32, &'static str> {
+    if depth == RECURSION_LIMIT {
+        return eval_iterative(time, expression, arithmetic);
+    }
+
+    match *expression {
+        Time => Ok(time),
+        Num(Int(i)) => Ok(i),
+        Num(Float(f)) => Ok(f as i32),
+        UnExpr(ref op, ref expr) => {
+            eval_recursive(time, expr, depth + 1, arithmetic).and_then(|x| (arithmetic.unop)(op, x))
+        }
+        BinExpr(ref expr1, ref op, ref expr2) => {
+            eval_recursive(time, expr1, depth + 1, arithmetic).and_then(|a| {
+                eval_recursive(time, expr2, depth + 1, arithmetic)
+                    .and_then(|b| (arithmetic.binop)(op, a, b))
             })
         }
     }
 }
 
-fn eval_unop(op: &UnOp, value: i32) -> Result<i32, &'static str> {
-    match op {
-        &Neg => Ok(-value),
-        &BoolNot => Ok(!value),
-        &BitNot => Ok(!value),
+enum Step<'a> {
+    Eval(&'a Expr),
+    Unary(UnOp),
+    Binary(BinOp),
+}
+
+/// Evaluates operands left to right and stops at the first error, just like
+/// the recursive evaluator.
+fn eval_iterative(time: i32, expression: &Expr, arithmetic: Arithmetic) -> Result<i32, &'static str> {
+    let mut steps = vec![Step::Eval(expression)];
+    let mut values = Vec::new();
+
+    while let Some(step) = steps.pop() {
+        match step {
+            Step::Eval(&Time) => values.push(time),
+            Step::Eval(&Num(Int(i))) => values.push(i),
+            Step::Eval(&Num(Float(f))) => values.push(f as i32),
+            Step::Eval(&UnExpr(op, ref expr)) => {
+                steps.push(Step::Unary(op));
+                steps.push(Step::Eval(expr));
+            }
+            Step::Eval(&BinExpr(ref expr1, op, ref expr2)) => {
+                steps.push(Step::Binary(op));
+                steps.push(Step::Eval(expr2));
+                steps.push(Step::Eval(expr1));
+            }
+            Step::Unary(op) => {
+                let x = values.pop().expect("operand was evaluated");
+                values.push((arithmetic.unop)(&op, x)?);
+            }
+            Step::Binary(op) => {
+                let b = values.pop().expect("operand was evaluated");
+                let a = values.pop().expect("operand was evaluated");
+                values.push((arithmetic.binop)(&op, a, b)?);
+            }
+        }
     }
+
+    Ok(values.pop().expect("expression was evaluated"))
 }
 
-fn eval_binop(op: &BinOp, a: i32, b: i32) -> Result<i32, &'static str> {
-    match op {
-        &Two(BinOp2::Add) => Ok(a + b),
-        &Two(BinOp2::Sub) => Ok(a - b),
-        &One(BinOp1::Mul) => Ok(a * b),
-        &One(BinOp1::Div) => {
+pub(crate) fn eval_unop(op: &UnOp, value: i32) -> Result<i32, &'static str> {
+    match *op {
+        Neg => Ok(-value),
+        BoolNot => Ok(!value),
+        BitNot => Ok(!value),
+    }
+}
+
+pub(crate) fn eval_binop(op: &BinOp, a: i32, b: i32) -> Result<i32, &'static str> {
+    match *op {
+        Two(BinOp2::Add) => Ok(a + b),
+        Two(BinOp2::Sub) => Ok(a - b),
+        One(BinOp1::Mul) => Ok(a * b),
+        One(BinOp1::Div) => {
             if b == 0 {
                 Err("division by 0")
             } else {
                 Ok(a / b)
             }
         },
-        &Three(BitShift::Right) => Ok(a >> b),
-        &Three(BitShift::Left) => Ok(a << b),
-        &Four(BitAnd) => Ok(a & b),
-        &Five(BitXOr) => Ok(a ^ b),
-        &Six(BitOr) => Ok(a | b),
+        Three(BitShift::Right) => Ok(a >> b),
+        Three(BitShift::Left) => Ok(a << b),
+        Four(BitAnd) => Ok(a & b),
+        Five(BitXOr) => Ok(a ^ b),
+        Six(BitOr) => Ok(a | b),
+    }
+}
+
+fn wrapping_unop(op: &UnOp, value: i32) -> Result<i32, &'static str> {
+    match *op {
+        Neg => Ok(value.wrapping_neg()),
+        BoolNot | BitNot => Ok(!value),
+    }
+}
+
+fn wrapping_binop(op: &BinOp, a: i32, b: i32) -> Result<i32, &'static str> {
+    match *op {
+        Two(BinOp2::Add) => Ok(a.wrapping_add(b)),
+        Two(BinOp2::Sub) => Ok(a.wrapping_sub(b)),
+        One(BinOp1::Mul) => Ok(a.wrapping_mul(b)),
+        One(BinOp1::Div) => match b {
+            0 => Err("division by 0"),
+            _ => a.checked_div(b).ok_or("division overflows"),
+        },
+        Three(BitShift::Right) => Ok(a.wrapping_shr(b as u32)),
+        Three(BitShift::Left) => Ok(a.wrapping_shl(b as u32)),
+        _ => eval_binop(op, a, b),
+    }
+}
+
+/// An expression together with the scratch buffers `eval_block` needs, so
+/// rendering block after block doesn't allocate, and the values of its
+/// slow-varying sub-expressions, so they are only recomputed when they
+/// change.
+#[derive(Clone, Debug)]
+pub struct EvalContext {
+    expression: Expr,
+    /// `None` if nothing can be cached, or if the tree is too deep to walk
+    /// recursively.
+    cache: Option<CachedExpr>,
+    failed: Vec<bool>,
+    scratch: Vec<Vec<i32>>,
+}
+
+impl EvalContext {
+    pub fn new(expression: Expr) -> Self {
+        let cache = if expression.depth() <= RECURSION_LIMIT {
+            Some(CachedExpr::new(&expression)).filter(|cache| cache.cached_count() > 0)
+        } else {
+            None
+        };
+        EvalContext {
+            expression,
+            cache,
+            failed: Vec::new(),
+            scratch: Vec::new(),
+        }
+    }
+
+    /// Whether slow-varying sub-expressions are cached between samples.
+    pub fn is_cached(&self) -> bool {
+        self.cache.is_some()
+    }
+
+    pub fn expression(&self) -> &Expr {
+        &self.expression
+    }
+
+    /// Which samples of the last block `eval` would have returned an error
+    /// for. Those samples are written as 0.
+    pub fn failed(&self) -> &[bool] {
+        &self.failed
+    }
+}
+
+/// Evaluates the samples `t_start, t_start + 1, ...` into `out`.
+///
+/// Each operator is applied to the whole block in one loop, which the
+/// compiler can vectorise, instead of walking the tree once per sample.
+/// Every sample comes out as `eval` gives it: once a sample has failed, no
+/// further operator is applied to it, just as `eval` stops at the first
+/// error. Sub-expressions that `cache::rate` finds slow are computed once
+/// per run of samples they have the same value for, and kept from one
+/// block to the next.
+pub fn eval_block(ctx: &mut EvalContext, t_start: i32, out: &mut [i32]) {
+    ctx.failed.clear();
+    ctx.failed.resize(out.len(), false);
+    match ctx.cache {
+        Some(ref mut cache) => cache.eval_block(t_start, out, &mut ctx.failed, &mut ctx.scratch),
+        None => eval_into(&ctx.expression, t_start, out, &mut ctx.failed, &mut ctx.scratch, 0),
+    }
+
+    for (x, &failed) in out.iter_mut().zip(ctx.failed.iter()) {
+        if failed {
+            *x = 0;
+        }
+    }
+}
+
+fn eval_into(
+    expression: &Expr,
+    t_start: i32,
+    out: &mut [i32],
+    failed: &mut [bool],
+    scratch: &mut Vec<Vec<i32>>,
+    depth: usize,
+) {
+    // like `eval`, deep sub-trees are evaluated with an explicit stack, a
+    // sample at a time
+    if depth == RECURSION_LIMIT {
+        for (i, (x, failed)) in out.iter_mut().zip(failed.iter_mut()).enumerate() {
+            if *failed {
+                continue;
+            }
+            match eval_iterative(t_start.wrapping_add(i as i32), expression, PLAIN) {
+                Ok(value) => *x = value,
+                Err(_) => *failed = true,
+            }
+        }
+        return;
+    }
+
+    match *expression {
+        Time => {
+            for (i, x) in out.iter_mut().enumerate() {
+                *x = t_start.wrapping_add(i as i32);
+            }
+        }
+        Num(Int(i)) => {
+            for x in out.iter_mut() {
+                *x = i;
+            }
+        }
+        Num(Float(f)) => {
+            for x in out.iter_mut() {
+                *x = f as i32;
+            }
+        }
+        UnExpr(op, ref expr) => {
+            eval_into(expr, t_start, out, failed, scratch, depth + 1);
+            eval_unop_block(op, out, failed);
+        }
+        BinExpr(ref expr1, op, ref expr2) => {
+            let mut rhs = scratch.pop().unwrap_or_default();
+            rhs.clear();
+            rhs.resize(out.len(), 0);
+
+            eval_into(expr1, t_start, out, failed, scratch, depth + 1);
+            eval_into(expr2, t_start, &mut rhs, failed, scratch, depth + 1);
+            eval_binop_block(op, out, &rhs, failed);
+
+            scratch.push(rhs);
+        }
+    }
+}
+
+pub(crate) fn eval_unop_block(op: UnOp, out: &mut [i32], failed: &[bool]) {
+    if failed.contains(&true) {
+        for (x, &failed) in out.iter_mut().zip(failed.iter()) {
+            if !failed {
+                *x = eval_unop(&op, *x).expect("unary operators don't fail");
+            }
+        }
+        return;
+    }
+    match op {
+        Neg => for x in out.iter_mut() { *x = -*x },
+        BoolNot | BitNot => for x in out.iter_mut() { *x = !*x },
+    }
+}
+
+pub(crate) fn eval_binop_block(op: BinOp, a: &mut [i32], b: &[i32], failed: &mut [bool]) {
+    // the values of failed samples are left over from whatever came before,
+    // and applying an operator to them could overflow where `eval` wouldn't
+    if op == One(BinOp1::Div) || failed.contains(&true) {
+        for ((a, &b), failed) in a.iter_mut().zip(b.iter()).zip(failed.iter_mut()) {
+            if !*failed {
+                match eval_binop(&op, *a, b) {
+                    Ok(x) => *a = x,
+                    Err(_) => *failed = true,
+                }
+            }
+        }
+        return;
+    }
+
+    let pairs = a.iter_mut().zip(b.iter());
+    match op {
+        Two(BinOp2::Add) => for (a, &b) in pairs { *a += b },
+        Two(BinOp2::Sub) => for (a, &b) in pairs { *a -= b },
+        One(BinOp1::Mul) => for (a, &b) in pairs { *a *= b },
+        One(BinOp1::Div) => unreachable!("division is applied a sample at a time"),
+        Three(BitShift::Right) => for (a, &b) in pairs { *a >>= b },
+        Three(BitShift::Left) => for (a, &b) in pairs { *a <<= b },
+        Four(BitAnd) => for (a, &b) in pairs { *a &= b },
+        Five(BitXOr) => for (a, &b) in pairs { *a ^= b },
+        Six(BitOr) => for (a, &b) in pairs { *a |= b },
     }
 }
@@ -8,3 +8,171 @@ pub enum Expr {
     UnExpr(UnOp, Box<Expr>),
     BinExpr(Box<Expr>, BinOp, Box<Expr>),
 }
+
+impl Expr {
+    /// The operands of this expression, left to right.
+    pub fn children(&self) -> Children<'_> {
+        let children = match *self {
+            Expr::Time | Expr::Num(_) => [None, None],
+            Expr::UnExpr(_, ref expr) => [Some(&**expr), None],
+            Expr::BinExpr(ref expr1, _, ref expr2) => [Some(&**expr1), Some(&**expr2)],
+        };
+        Children { children, next: 0 }
+    }
+
+    /// This expression and every expression nested in it, parents before
+    /// their operands and left operands before right ones.
+    pub fn subexpressions(&self) -> SubExpressions<'_> {
+        SubExpressions { stack: vec![self] }
+    }
+
+    /// How many operators, literals and `t` the expression is made of.
+    pub fn node_count(&self) -> usize {
+        self.subexpressions().count()
+    }
+
+    /// How many operators are applied to compute the expression.
+    pub fn op_count(&self) -> usize {
+        self.subexpressions()
+            .filter(|expr| match **expr {
+                Expr::UnExpr(..) | Expr::BinExpr(..) => true,
+                Expr::Time | Expr::Num(_) => false,
+            })
+            .count()
+    }
+
+    /// The number of nodes on the longest path from the root to a leaf.
+    pub fn depth(&self) -> usize {
+        let mut deepest = 0;
+        let mut stack = vec![(self, 1)];
+        while let Some((expr, depth)) = stack.pop() {
+            deepest = deepest.max(depth);
+            stack.extend(expr.children().map(|child| (child, depth + 1)));
+        }
+        deepest
+    }
+
+    /// The value of a literal, truncated as `eval` truncates a float, or of
+    /// a negated one such as `-1`. `None` for anything else.
+    pub fn literal(&self) -> Option<i32> {
+        match *self {
+            Expr::Num(Numeral::Int(i)) => Some(i),
+            Expr::Num(Numeral::Float(f)) => Some(f as i32),
+            Expr::UnExpr(UnOp::Neg, ref expr) => expr.literal().map(i32::wrapping_neg),
+            _ => None,
+        }
+    }
+}
+
+/// Iterator over the operands of an expression, from `Expr::children`.
+pub struct Children<'a> {
+    children: [Option<&'a Expr>; 2],
+    next: usize,
+}
+
+impl<'a> Iterator for Children<'a> {
+    type Item = &'a Expr;
+
+    fn next(&mut self) -> Option<&'a Expr> {
+        let child = self.children.get(self.next).cloned().and_then(|child| child);
+        self.next += 1;
+        child
+    }
+}
+
+/// Iterator over an expression and everything nested in it, from
+/// `Expr::subexpressions`. It keeps its own stack, so it handles any depth.
+pub struct SubExpressions<'a> {
+    stack: Vec<&'a Expr>,
+}
+
+impl<'a> Iterator for SubExpressions<'a> {
+    type Item = &'a Expr;
+
+    fn next(&mut self) -> Option<&'a Expr> {
+        let expr = self.stack.pop()?;
+        match *expr {
+            Expr::Time | Expr::Num(_) => {}
+            Expr::UnExpr(_, ref expr) => self.stack.push(expr),
+            Expr::BinExpr(ref expr1, _, ref expr2) => {
+                self.stack.push(expr2);
+                self.stack.push(expr1);
+            }
+        }
+        Some(expr)
+    }
+}
+
+/// Walks an expression without changing it.
+///
+/// Every method defaults to visiting the operands of the node it is given,
+/// so an implementation only overrides the nodes it cares about. An override
+/// of `visit` calls `walk` to keep going into the operands.
+pub trait Visitor {
+    fn visit(&mut self, expression: &Expr) {
+        walk(self, expression)
+    }
+
+    fn visit_time(&mut self) {}
+
+    fn visit_num(&mut self, _num: Numeral) {}
+
+    fn visit_unexpr(&mut self, _op: UnOp, expr: &Expr) {
+        self.visit(expr)
+    }
+
+    fn visit_binexpr(&mut self, expr1: &Expr, _op: BinOp, expr2: &Expr) {
+        self.visit(expr1);
+        self.visit(expr2)
+    }
+}
+
+/// Calls the `Visitor` method for the kind of node `expression` is.
+pub fn walk<V: Visitor + ?Sized>(visitor: &mut V, expression: &Expr) {
+    match *expression {
+        Expr::Time => visitor.visit_time(),
+        Expr::Num(num) => visitor.visit_num(num),
+        Expr::UnExpr(op, ref expr) => visitor.visit_unexpr(op, expr),
+        Expr::BinExpr(ref expr1, op, ref expr2) => visitor.visit_binexpr(expr1, op, expr2),
+    }
+}
+
+/// Rebuilds an expression bottom-up.
+///
+/// Every method defaults to folding the operands of the node it is given
+/// and putting the node back together around them, so an implementation
+/// only overrides the nodes it rewrites. An override of `fold` calls
+/// `walk_fold` to keep going into the operands.
+pub trait Fold {
+    fn fold(&mut self, expression: Expr) -> Expr {
+        walk_fold(self, expression)
+    }
+
+    fn fold_time(&mut self) -> Expr {
+        Expr::Time
+    }
+
+    fn fold_num(&mut self, num: Numeral) -> Expr {
+        Expr::Num(num)
+    }
+
+    fn fold_unexpr(&mut self, op: UnOp, expr: Expr) -> Expr {
+        Expr::UnExpr(op, Box::new(self.fold(expr)))
+    }
+
+    fn fold_binexpr(&mut self, expr1: Expr, op: BinOp, expr2: Expr) -> Expr {
+        let expr1 = self.fold(expr1);
+        let expr2 = self.fold(expr2);
+        Expr::BinExpr(Box::new(expr1), op, Box::new(expr2))
+    }
+}
+
+/// Calls the `Fold` method for the kind of node `expression` is.
+pub fn walk_fold<F: Fold + ?Sized>(folder: &mut F, expression: Expr) -> Expr {
+    match expression {
+        Expr::Time => folder.fold_time(),
+        Expr::Num(num) => folder.fold_num(num),
+        Expr::UnExpr(op, expr) => folder.fold_unexpr(op, *expr),
+        Expr::BinExpr(expr1, op, expr2) => folder.fold_binexpr(*expr1, op, *expr2),
+    }
+}
@@ -0,0 +1,194 @@
+use expr::Expr;
+use parser::{parse_with_comments, Comment};
+use printer::{groups_left, groups_right, groups_unary_operand, print, Style};
+use self::Expr::*;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Config {
+    /// Lines are broken to stay within this many characters where possible.
+    pub width: usize,
+    /// Whether `format_source` keeps `//` comments.
+    pub keep_comments: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            width: 80,
+            keep_comments: true,
+        }
+    }
+}
+
+/// The result of `check`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Check {
+    Formatted,
+    /// Formatting would change the source, starting at this line (from 1).
+    Unformatted { line: usize },
+}
+
+/// Lays `expression` out over as many lines as it takes to fit in
+/// `config.width`, ending with a line break.
+///
+/// An expression that fits is printed on one line in the spaced style.
+/// One that doesn't is split at its loosest operators, one operand per
+/// line, with the operators of that level lined up in a column before their
+/// operands. Operands that still don't fit are split the same way.
+pub fn format(expression: &Expr, config: &Config) -> String {
+    join(layout(expression, config.width), &[])
+}
+
+/// Parses `source` and formats it, keeping its comments if
+/// `config.keep_comments` is set. A comment is put on its own line, before
+/// the line that holds the literal or `t` that followed it.
+pub fn format_source(source: &str, config: &Config) -> Result<String, String> {
+    let (expression, comments) = parse_with_comments(source)?;
+    let comments = if config.keep_comments {
+        comments
+    } else {
+        Vec::new()
+    };
+    Ok(join(layout(&expression, config.width), &comments))
+}
+
+/// Whether `source` is exactly what `format_source` makes of it.
+pub fn check(source: &str, config: &Config) -> Result<Check, String> {
+    let formatted = format_source(source, config)?;
+    if formatted == source {
+        return Ok(Check::Formatted);
+    }
+
+    let mut expected = formatted.lines();
+    let mut found = source.lines();
+    let mut line = 1;
+    loop {
+        match (expected.next(), found.next()) {
+            (Some(a), Some(b)) if a == b => line += 1,
+            // every line matches, so the line breaks differ
+            (None, None) => return Ok(Check::Unformatted { line: (line - 1).max(1) }),
+            _ => return Ok(Check::Unformatted { line }),
+        }
+    }
+}
+
+/// A line of output, without its line break.
+struct Line {
+    text: String,
+    /// The number of literals and `t`s on the line.
+    leaves: usize,
+}
+
+fn layout(expression: &Expr, width: usize) -> Vec<Line> {
+    let flat = print(expression, Style::Spaced);
+    if flat.len() <= width || !expression.subexpressions().any(is_binary) {
+        return vec![Line {
+            text: flat,
+            leaves: leaves(expression),
+        }];
+    }
+
+    match *expression {
+        UnExpr(op, ref expr) => {
+            let prefix = op.symbol();
+            let grouped = groups_unary_operand(expr);
+            prefixed(prefix, operand(expr, grouped, width.saturating_sub(prefix.len())))
+        }
+        BinExpr(..) => chain(expression, width),
+        Time | Num(_) => unreachable!("a leaf always fits"),
+    }
+}
+
+/// Splits a run of operators of one precedence level, as in `a + b - c`,
+/// one operand per line.
+fn chain(expression: &Expr, width: usize) -> Vec<Line> {
+    let mut operands = Vec::new();
+    let mut ops = Vec::new();
+    let mut rest = expression;
+    while let BinExpr(ref expr1, op, ref expr2) = *rest {
+        operands.push((&**expr1, groups_left(op, expr1)));
+        ops.push(op);
+        // the right operand continues the run if it's on the same level
+        match **expr2 {
+            BinExpr(_, next, _) if next.level() == op.level() => rest = expr2,
+            _ => {
+                operands.push((&**expr2, groups_right(op, expr2)));
+                break;
+            }
+        }
+    }
+
+    let column = ops.iter().map(|op| op.symbol().len()).max().unwrap_or(0);
+    let inner = width.saturating_sub(column + 1);
+    let mut lines = Vec::new();
+    for (i, &(expr, grouped)) in operands.iter().enumerate() {
+        let prefix = match i {
+            0 => " ".repeat(column + 1),
+            _ => format!("{:<width$} ", ops[i - 1].symbol(), width = column),
+        };
+        lines.extend(prefixed(&prefix, operand(expr, grouped, inner)));
+    }
+    lines
+}
+
+fn operand(expression: &Expr, grouped: bool, width: usize) -> Vec<Line> {
+    if !grouped {
+        return layout(expression, width);
+    }
+
+    let mut lines = prefixed("(", layout(expression, width.saturating_sub(2)));
+    if let Some(last) = lines.last_mut() {
+        last.text.push(')');
+    }
+    lines
+}
+
+/// Puts `prefix` before the first line and lines the others up after it.
+fn prefixed(prefix: &str, lines: Vec<Line>) -> Vec<Line> {
+    let indent = " ".repeat(prefix.len());
+    lines
+        .into_iter()
+        .enumerate()
+        .map(|(i, line)| Line {
+            text: format!("{}{}", if i == 0 { prefix } else { &indent }, line.text),
+            leaves: line.leaves,
+        })
+        .collect()
+}
+
+fn join(lines: Vec<Line>, comments: &[Comment]) -> String {
+    let mut out = String::new();
+    let mut comments = comments.iter().peekable();
+    let mut leaves = 0;
+    for line in lines {
+        leaves += line.leaves;
+        let indent = line.text.len() - line.text.trim_start().len();
+        while let Some(comment) = comments.next_if(|comment| comment.leaves_before < leaves) {
+            write_comment(&line.text[..indent], comment, &mut out);
+        }
+        out.push_str(line.text.trim_end());
+        out.push('\n');
+    }
+    for comment in comments {
+        write_comment("", comment, &mut out);
+    }
+    out
+}
+
+fn write_comment(indent: &str, comment: &Comment, out: &mut String) {
+    out.push_str(indent);
+    out.push_str("//");
+    out.push_str(comment.text.trim_end());
+    out.push('\n');
+}
+
+fn is_binary(expression: &Expr) -> bool {
+    matches!(*expression, BinExpr(..))
+}
+
+fn leaves(expression: &Expr) -> usize {
+    expression
+        .subexpressions()
+        .filter(|expr| matches!(**expr, Time | Num(_)))
+        .count()
+}
@@ -0,0 +1,268 @@
+use eval::eval_wrapping;
+use expr::Expr;
+
+/// An expression prepared for rendering whole blocks of samples.
+///
+/// With the `jit` feature enabled the expression is compiled to native code
+/// with Cranelift. When the feature is off, or the host can't be targeted,
+/// blocks are rendered by the interpreter instead.
+pub struct Compiled {
+    expression: Expr,
+    native: Result<native::Block, String>,
+}
+
+pub fn compile(expression: &Expr) -> Compiled {
+    Compiled {
+        expression: expression.clone(),
+        native: native::Block::new(expression),
+    }
+}
+
+impl Compiled {
+    pub fn is_native(&self) -> bool {
+        self.native.is_ok()
+    }
+
+    /// Why the expression is rendered by the interpreter, if it is.
+    pub fn fallback_reason(&self) -> Option<&str> {
+        self.native.as_ref().err().map(|e| e.as_str())
+    }
+
+    /// Evaluates the samples `t_start, t_start + 1, ...` into `out`.
+    ///
+    /// `failed[i]` is set when `eval` would have returned an error for
+    /// sample `i`, in which case `out[i]` is 0. Arithmetic wraps the way
+    /// `eval` does in a release build, and a sample that would make `eval`
+    /// panic (`i32::MIN / -1`) is reported as failed.
+    pub fn eval_block(&self, t_start: i32, out: &mut [i32], failed: &mut [bool]) {
+        assert_eq!(out.len(), failed.len());
+
+        if let Ok(ref block) = self.native {
+            return block.run(t_start, out, failed);
+        }
+
+        for (i, (sample, failure)) in out.iter_mut().zip(failed.iter_mut()).enumerate() {
+            match eval_wrapping(t_start.wrapping_add(i as i32), &self.expression) {
+                Ok(x) => {
+                    *sample = x;
+                    *failure = false;
+                }
+                Err(_) => {
+                    *sample = 0;
+                    *failure = true;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(feature = "jit")]
+mod native {
+    use std::mem;
+
+    use cranelift_codegen::entity::EntityRef;
+    use cranelift_codegen::ir::condcodes::IntCC;
+    use cranelift_codegen::ir::{types, AbiParam, Block as IrBlock, InstBuilder, MemFlags, Value};
+    use cranelift_codegen::settings::{self, Configurable};
+    use cranelift_frontend::{FunctionBuilder, FunctionBuilderContext, Variable};
+    use cranelift_jit::{JITBuilder, JITModule};
+    use cranelift_module::{default_libcall_names, Linkage, Module};
+
+    use expr::Expr;
+    use numeral::Numeral::*;
+    use ops::*;
+    use parser::MAX_DEPTH;
+    use self::Expr::*;
+
+    type BlockFn = unsafe extern "C" fn(i32, *mut i32, *mut bool, usize);
+
+    pub struct Block {
+        module: Option<JITModule>,
+        function: BlockFn,
+    }
+
+    impl Block {
+        pub fn new(expression: &Expr) -> Result<Self, String> {
+            // `emit` recurses once per level
+            let depth = expression.depth();
+            if depth > MAX_DEPTH {
+                return Err(format!("expression is nested {} deep, more than {}", depth, MAX_DEPTH));
+            }
+
+            let mut flags = settings::builder();
+            flags.set("opt_level", "speed").map_err(|e| e.to_string())?;
+            let isa = cranelift_native::builder()?
+                .finish(settings::Flags::new(flags))
+                .map_err(|e| e.to_string())?;
+            let mut module = JITModule::new(JITBuilder::with_isa(isa, default_libcall_names()));
+
+            let pointer = module.target_config().pointer_type();
+            let mut ctx = module.make_context();
+            {
+                let params = &mut ctx.func.signature.params;
+                params.push(AbiParam::new(types::I32));
+                params.push(AbiParam::new(pointer));
+                params.push(AbiParam::new(pointer));
+                params.push(AbiParam::new(pointer));
+            }
+
+            let mut builder_ctx = FunctionBuilderContext::new();
+            {
+                let mut b = FunctionBuilder::new(&mut ctx.func, &mut builder_ctx);
+                let index = Variable::new(0);
+                b.declare_var(index, pointer);
+
+                let entry = b.create_block();
+                let header = b.create_block();
+                let body = b.create_block();
+                let fail = b.create_block();
+                let next = b.create_block();
+                let exit = b.create_block();
+
+                b.append_block_params_for_function_params(entry);
+                b.switch_to_block(entry);
+                let t_start = b.block_params(entry)[0];
+                let out = b.block_params(entry)[1];
+                let failed = b.block_params(entry)[2];
+                let len = b.block_params(entry)[3];
+                let zero = b.ins().iconst(pointer, 0);
+                b.def_var(index, zero);
+                b.ins().jump(header, &[]);
+
+                b.switch_to_block(header);
+                let i = b.use_var(index);
+                let more = b.ins().icmp(IntCC::UnsignedLessThan, i, len);
+                b.ins().brif(more, body, &[], exit, &[]);
+
+                b.switch_to_block(body);
+                let i = b.use_var(index);
+                let offset = b.ins().ireduce(types::I32, i);
+                let time = b.ins().iadd(t_start, offset);
+                let value = emit(&mut b, expression, time, fail);
+                let sample_addr = sample_address(&mut b, out, i);
+                b.ins().store(MemFlags::trusted(), value, sample_addr, 0);
+                let ok = b.ins().iconst(types::I8, 0);
+                let flag_addr = b.ins().iadd(failed, i);
+                b.ins().store(MemFlags::trusted(), ok, flag_addr, 0);
+                b.ins().jump(next, &[]);
+
+                b.switch_to_block(fail);
+                let i = b.use_var(index);
+                let sample_addr = sample_address(&mut b, out, i);
+                let silence = b.ins().iconst(types::I32, 0);
+                b.ins().store(MemFlags::trusted(), silence, sample_addr, 0);
+                let err = b.ins().iconst(types::I8, 1);
+                let flag_addr = b.ins().iadd(failed, i);
+                b.ins().store(MemFlags::trusted(), err, flag_addr, 0);
+                b.ins().jump(next, &[]);
+
+                b.switch_to_block(next);
+                let i = b.use_var(index);
+                let i = b.ins().iadd_imm(i, 1);
+                b.def_var(index, i);
+                b.ins().jump(header, &[]);
+
+                b.switch_to_block(exit);
+                b.ins().return_(&[]);
+
+                b.seal_all_blocks();
+                b.finalize();
+            }
+
+            let id = module
+                .declare_function("eval_block", Linkage::Local, &ctx.func.signature)
+                .map_err(|e| e.to_string())?;
+            module
+                .define_function(id, &mut ctx)
+                .map_err(|e| e.to_string())?;
+            module.clear_context(&mut ctx);
+            module.finalize_definitions().map_err(|e| e.to_string())?;
+
+            let code = module.get_finalized_function(id);
+            Ok(Block {
+                module: Some(module),
+                function: unsafe { mem::transmute::<*const u8, BlockFn>(code) },
+            })
+        }
+
+        pub fn run(&self, t_start: i32, out: &mut [i32], failed: &mut [bool]) {
+            unsafe { (self.function)(t_start, out.as_mut_ptr(), failed.as_mut_ptr(), out.len()) }
+        }
+    }
+
+    impl Drop for Block {
+        fn drop(&mut self) {
+            if let Some(module) = self.module.take() {
+                unsafe { module.free_memory() }
+            }
+        }
+    }
+
+    fn sample_address(b: &mut FunctionBuilder, out: Value, i: Value) -> Value {
+        let offset = b.ins().imul_imm(i, 4);
+        b.ins().iadd(out, offset)
+    }
+
+    fn emit(b: &mut FunctionBuilder, expression: &Expr, time: Value, fail: IrBlock) -> Value {
+        match *expression {
+            Time => time,
+            // an `I32` immediate is given as its bits, not sign-extended
+            Num(Int(i)) => b.ins().iconst(types::I32, i64::from(i as u32)),
+            Num(Float(f)) => b.ins().iconst(types::I32, i64::from(f as i32 as u32)),
+            UnExpr(op, ref expr) => {
+                let x = emit(b, expr, time, fail);
+                match op {
+                    UnOp::Neg => b.ins().ineg(x),
+                    UnOp::BoolNot | UnOp::BitNot => b.ins().bnot(x),
+                }
+            }
+            BinExpr(ref expr1, op, ref expr2) => {
+                let x = emit(b, expr1, time, fail);
+                let y = emit(b, expr2, time, fail);
+                match op {
+                    BinOp::Two(BinOp2::Add) => b.ins().iadd(x, y),
+                    BinOp::Two(BinOp2::Sub) => b.ins().isub(x, y),
+                    BinOp::One(BinOp1::Mul) => b.ins().imul(x, y),
+                    BinOp::One(BinOp1::Div) => emit_div(b, x, y, fail),
+                    BinOp::Three(BitShift::Right) => b.ins().sshr(x, y),
+                    BinOp::Three(BitShift::Left) => b.ins().ishl(x, y),
+                    BinOp::Four(BitAnd) => b.ins().band(x, y),
+                    BinOp::Five(BitXOr) => b.ins().bxor(x, y),
+                    BinOp::Six(BitOr) => b.ins().bor(x, y),
+                }
+            }
+        }
+    }
+
+    // `sdiv` traps on a zero divisor and on `i32::MIN / -1`, so both are
+    // routed to the failure block before dividing.
+    fn emit_div(b: &mut FunctionBuilder, x: Value, y: Value, fail: IrBlock) -> Value {
+        let divide = b.create_block();
+        let by_zero = b.ins().icmp_imm(IntCC::Equal, y, 0);
+        let min = b.ins().icmp_imm(IntCC::Equal, x, i64::from(i32::MIN));
+        let minus_one = b.ins().icmp_imm(IntCC::Equal, y, -1);
+        let overflow = b.ins().band(min, minus_one);
+        let bad = b.ins().bor(by_zero, overflow);
+        b.ins().brif(bad, fail, &[], divide, &[]);
+
+        b.switch_to_block(divide);
+        b.ins().sdiv(x, y)
+    }
+}
+
+#[cfg(not(feature = "jit"))]
+mod native {
+    use expr::Expr;
+
+    pub enum Block {}
+
+    impl Block {
+        pub fn new(_: &Expr) -> Result<Self, String> {
+            Err("built without the `jit` feature".to_owned())
+        }
+
+        pub fn run(&self, _: i32, _: &mut [i32], _: &mut [bool]) {
+            match *self {}
+        }
+    }
+}
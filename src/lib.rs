@@ -1,14 +1,47 @@
+#[cfg(feature = "audio")]
 extern crate hound;
 #[macro_use]
 extern crate nom;
+#[cfg(feature = "audio")]
 extern crate portaudio as pa;
+#[cfg(feature = "audio")]
 extern crate sample;
+#[cfg(feature = "jit")]
+extern crate cranelift_codegen;
+#[cfg(feature = "jit")]
+extern crate cranelift_frontend;
+#[cfg(feature = "jit")]
+extern crate cranelift_jit;
+#[cfg(feature = "jit")]
+extern crate cranelift_module;
+#[cfg(feature = "jit")]
+extern crate cranelift_native;
 
+pub mod bits;
+pub mod build;
+pub mod cache;
+pub mod canonical;
+pub mod dag;
+pub mod diff;
+pub mod equivalence;
 pub mod expr;
+pub mod formatter;
 pub mod eval;
+pub mod jit;
+pub mod limits;
+pub mod lint;
+pub mod minify;
 pub mod numeral;
 pub mod ops;
+pub mod optimize;
 pub mod parser;
+pub mod period;
+pub mod printer;
+pub mod range;
+#[cfg(feature = "audio")]
 pub mod player;
+#[cfg(feature = "audio")]
 pub mod signal;
+pub mod syntax;
+#[cfg(feature = "audio")]
 pub mod wav;
@@ -0,0 +1,119 @@
+use std::error;
+use std::fmt;
+
+use expr::Expr;
+
+/// Caps on how much work a single untrusted expression may cause.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Limits {
+    /// Longest accepted source text, in bytes.
+    pub max_source_len: usize,
+    /// Deepest accepted expression tree.
+    pub max_depth: usize,
+    /// Most nodes (operators, literals and `t`) in the expression tree.
+    pub max_nodes: usize,
+    /// Most operators evaluated to produce a single sample.
+    pub max_ops_per_sample: usize,
+    /// Longest render, in seconds.
+    pub max_duration: f32,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            max_source_len: 1024,
+            max_depth: 64,
+            max_nodes: 512,
+            max_ops_per_sample: 256,
+            max_duration: 300.0,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Error {
+    Syntax(String),
+    SourceTooLong { len: usize, max: usize },
+    TooDeep { depth: usize, max: usize },
+    TooManyNodes { nodes: usize, max: usize },
+    TooManyOps { ops: usize, max: usize },
+    DurationTooLong { duration: f32, max: f32 },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Syntax(ref e) => write!(f, "Syntax error: {}", e),
+            Error::SourceTooLong { len, max } =>
+                write!(f, "Expression is {} bytes long, the limit is {}", len, max),
+            Error::TooDeep { depth, max } =>
+                write!(f, "Expression is nested {} deep, the limit is {}", depth, max),
+            Error::TooManyNodes { nodes, max } =>
+                write!(f, "Expression has {} nodes, the limit is {}", nodes, max),
+            Error::TooManyOps { ops, max } =>
+                write!(f, "Expression needs {} operations per sample, the limit is {}", ops, max),
+            Error::DurationTooLong { duration, max } =>
+                write!(f, "Cannot render {} seconds, the limit is {}", duration, max),
+        }
+    }
+}
+
+impl error::Error for Error {}
+
+impl Limits {
+    /// Checks what can be checked before parsing: the length of the
+    /// source. How deep the expression nests is checked on the parsed tree,
+    /// so redundant parentheses don't count.
+    pub fn check_source(&self, source: &str) -> Result<(), Error> {
+        if source.len() > self.max_source_len {
+            return Err(Error::SourceTooLong {
+                len: source.len(),
+                max: self.max_source_len,
+            });
+        }
+        Ok(())
+    }
+
+    pub fn check_expr(&self, expression: &Expr) -> Result<(), Error> {
+        let depth = expression.depth();
+        if depth > self.max_depth {
+            return Err(Error::TooDeep {
+                depth,
+                max: self.max_depth,
+            });
+        }
+
+        let nodes = expression.node_count();
+        if nodes > self.max_nodes {
+            return Err(Error::TooManyNodes {
+                nodes,
+                max: self.max_nodes,
+            });
+        }
+
+        self.check_ops(expression)
+    }
+
+    /// Checks the cost of evaluating one sample. Without functions or loops
+    /// in the language, that is one operation per operator in the tree.
+    pub fn check_ops(&self, expression: &Expr) -> Result<(), Error> {
+        let ops = expression.op_count();
+        if ops > self.max_ops_per_sample {
+            return Err(Error::TooManyOps {
+                ops,
+                max: self.max_ops_per_sample,
+            });
+        }
+        Ok(())
+    }
+
+    pub fn check_duration(&self, duration: f32) -> Result<(), Error> {
+        if duration.is_nan() || duration > self.max_duration {
+            return Err(Error::DurationTooLong {
+                duration,
+                max: self.max_duration,
+            });
+        }
+        Ok(())
+    }
+}
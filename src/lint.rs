@@ -0,0 +1,322 @@
+use std::fmt;
+
+use bits::influence;
+use expr::Expr;
+use numeral::Numeral::*;
+use ops::*;
+use parser::{parse_with_spans, Span};
+use printer::{groups_left, groups_right, groups_unary_operand, print, Style};
+use range::{analyze, Interval};
+use self::Expr::*;
+use self::BinOp::*;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    /// Plays, though probably not as intended.
+    Warning,
+    /// Fails or panics when it is played.
+    Error,
+}
+
+/// What a lint is about.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Code {
+    /// `x/0`, which always fails.
+    DivisionByZero,
+    /// `x<<32` or `x>>-1`, which panics in a debug build and shifts by the
+    /// low 5 bits of the amount in a release build.
+    ShiftOutOfRange,
+    /// `x*1`, `x+0`, `x|0` and the like.
+    NoOp,
+    /// An operation whose result is the same for every `t`, so its operands
+    /// are masked away, as in `(t*3)&0`.
+    MaskedAway,
+    /// The samples are the same for every `t`, so nothing can be heard.
+    ConstantOutput,
+    /// A float literal with a fraction, which `eval` drops.
+    TruncatedFloat,
+}
+
+impl Code {
+    /// The short name of the lint, as in `L001`.
+    pub fn id(&self) -> &'static str {
+        match *self {
+            Code::DivisionByZero => "L001",
+            Code::ShiftOutOfRange => "L002",
+            Code::NoOp => "L003",
+            Code::MaskedAway => "L004",
+            Code::ConstantOutput => "L005",
+            Code::TruncatedFloat => "L006",
+        }
+    }
+
+    pub fn severity(&self) -> Severity {
+        match *self {
+            Code::DivisionByZero | Code::ShiftOutOfRange => Severity::Error,
+            Code::NoOp | Code::MaskedAway | Code::ConstantOutput | Code::TruncatedFloat => {
+                Severity::Warning
+            }
+        }
+    }
+}
+
+/// A suggested replacement for the node a lint is about.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Fix {
+    pub replacement: Expr,
+    /// The replacement as it can be put in place of the node's span, in
+    /// parentheses if the node's parent needs them.
+    pub text: String,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Lint {
+    pub code: Code,
+    pub severity: Severity,
+    pub message: String,
+    /// The node the lint is about, by its path from the root as in
+    /// `range::Report`.
+    pub path: Vec<usize>,
+    /// Where the node is in the source, if the lint came from `lint_source`.
+    pub span: Option<Span>,
+    pub fix: Option<Fix>,
+}
+
+impl Lint {
+    /// `expression` with the node the lint is about replaced by the
+    /// suggested fix, or `None` if there is no fix.
+    pub fn apply(&self, expression: &Expr) -> Option<Expr> {
+        self.fix
+            .as_ref()
+            .map(|fix| replace(expression, &self.path, &fix.replacement))
+    }
+}
+
+/// Prints as `warning[L003]: `t*1` does nothing (replace with `t`)`.
+impl fmt::Display for Lint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        write!(f, "{}[{}]: {}", severity, self.code.id(), self.message)?;
+        if let Some(span) = self.span {
+            write!(f, " at {}..{}", span.start, span.end)?;
+        }
+        if let Some(ref fix) = self.fix {
+            write!(f, " (replace with `{}`)", fix.text)?;
+        }
+        Ok(())
+    }
+}
+
+/// Finds likely mistakes in `expression`, root first. The lints have no
+/// spans.
+pub fn lint(expression: &Expr) -> Vec<Lint> {
+    let mut linter = Linter {
+        root: expression,
+        path: Vec::new(),
+        lints: Vec::new(),
+    };
+
+    linter.visit(expression);
+    let mut lints = linter.lints;
+
+    // a lint on the root already says as much
+    if influence(expression).sample() == 0 && !lints.iter().any(|lint| lint.path.is_empty()) {
+        let report = analyze(expression, time());
+        let fix = match report.output {
+            Some(value) if report.is_constant() => Some(Num(Int(value.range.lo))),
+            _ => None,
+        };
+        let message = "the samples are the same for every `t`".to_owned();
+        lints.insert(0, new_lint(expression, &[], Code::ConstantOutput, message, fix));
+    }
+    lints
+}
+
+/// Parses `source` and lints it, giving every lint the span of its node.
+pub fn lint_source(source: &str) -> Result<Vec<Lint>, String> {
+    let (expression, spans) = parse_with_spans(source)?;
+    let mut lints = lint(&expression);
+    for lint in &mut lints {
+        lint.span = Some(spans[preorder_index(&expression, &lint.path)]);
+    }
+    Ok(lints)
+}
+
+/// Playback starts at 0 and counts up.
+fn time() -> Interval {
+    Interval::new(0, i32::MAX)
+}
+
+struct Linter<'a> {
+    root: &'a Expr,
+    path: Vec<usize>,
+    lints: Vec<Lint>,
+}
+
+impl<'a> Linter<'a> {
+    fn visit(&mut self, expression: &Expr) {
+        match *expression {
+            Time | Num(Int(_)) => {}
+            Num(Float(f)) => {
+                if f.fract() != 0.0 {
+                    let message = format!("`{}` is truncated to {}", f, f as i32);
+                    self.report(Code::TruncatedFloat, message, Some(Num(Int(f as i32))));
+                }
+            }
+            UnExpr(_, ref expr) => self.visit_operand(0, expr),
+            BinExpr(ref expr1, op, ref expr2) => {
+                if !self.check_binary(expression, expr1, op, expr2) {
+                    self.visit_operand(0, expr1);
+                    self.visit_operand(1, expr2);
+                }
+            }
+        }
+    }
+
+    fn visit_operand(&mut self, index: usize, expression: &Expr) {
+        self.path.push(index);
+        self.visit(expression);
+        self.path.pop();
+    }
+
+    /// Reports what is wrong with the operation itself. Returns true if
+    /// its operands don't matter, so they need no lints of their own.
+    fn check_binary(&mut self, expression: &Expr, expr1: &Expr, op: BinOp, expr2: &Expr) -> bool {
+        // only printed when there is something to report
+        let text = || print(expression, Style::Compact);
+        match (op, expr1.literal(), expr2.literal()) {
+            (One(BinOp1::Div), _, Some(0)) => {
+                let message = format!("`{}` divides by 0, which always fails", text());
+                self.report(Code::DivisionByZero, message, None);
+            }
+            (Three(_), _, Some(b)) if !(0..32).contains(&b) => {
+                let message = format!("`{}` shifts by {}, outside of 0 to 31", text(), b);
+                let fix = BinExpr(Box::new(expr1.clone()), op, Box::new(Num(Int(b & 31))));
+                self.report(Code::ShiftOutOfRange, message, Some(fix));
+            }
+
+            (Two(BinOp2::Add), _, Some(0)) |
+            (Two(BinOp2::Sub), _, Some(0)) |
+            (One(BinOp1::Mul), _, Some(1)) |
+            (One(BinOp1::Div), _, Some(1)) |
+            (Three(_), _, Some(0)) |
+            (Four(BitAnd), _, Some(-1)) |
+            (Five(BitXOr), _, Some(0)) |
+            (Six(BitOr), _, Some(0)) => {
+                self.report(Code::NoOp, format!("`{}` does nothing", text()), Some(expr1.clone()));
+            }
+            (Two(BinOp2::Add), Some(0), _) |
+            (One(BinOp1::Mul), Some(1), _) |
+            (Four(BitAnd), Some(-1), _) |
+            (Five(BitXOr), Some(0), _) |
+            (Six(BitOr), Some(0), _) => {
+                self.report(Code::NoOp, format!("`{}` does nothing", text()), Some(expr2.clone()));
+            }
+
+            (One(BinOp1::Mul), _, _) | (Four(BitAnd), _, _) | (Six(BitOr), _, _)
+                if depends_on_time(expression) =>
+            {
+                let report = analyze(expression, time());
+                if let Some(value) = report.output {
+                    if value.range.lo == value.range.hi {
+                        let message = format!("`{}` is {} for every `t`", text(), value.range.lo);
+                        // dropping an operand that may fail or wrap would change the output
+                        let fix = if report.overflows.is_empty() && report.divisions_by_zero.is_empty() {
+                            Some(Num(Int(value.range.lo)))
+                        } else {
+                            None
+                        };
+                        self.report(Code::MaskedAway, message, fix);
+                        return true;
+                    }
+                }
+            }
+            _ => {}
+        }
+        false
+    }
+
+    fn report(&mut self, code: Code, message: String, replacement: Option<Expr>) {
+        let lint = new_lint(self.root, &self.path, code, message, replacement);
+        self.lints.push(lint);
+    }
+}
+
+fn new_lint(root: &Expr, path: &[usize], code: Code, message: String, replacement: Option<Expr>) -> Lint {
+    let fix = replacement.map(|replacement| Fix {
+        text: fix_text(root, path, &replacement),
+        replacement,
+    });
+    Lint {
+        code,
+        severity: code.severity(),
+        message,
+        path: path.to_vec(),
+        span: None,
+        fix,
+    }
+}
+
+fn depends_on_time(expression: &Expr) -> bool {
+    expression.subexpressions().any(|expr| *expr == Time)
+}
+
+fn child(expression: &Expr, index: usize) -> &Expr {
+    expression.children().nth(index).expect("the path leads to a node")
+}
+
+/// The printed replacement, grouped if the parent of the node at `path`
+/// would need parentheses around it.
+fn fix_text(root: &Expr, path: &[usize], replacement: &Expr) -> String {
+    let text = print(replacement, Style::Compact);
+    let (&index, parent_path) = match path.split_last() {
+        Some(last) => last,
+        None => return text,
+    };
+    let parent = parent_path.iter().fold(root, |node, &i| child(node, i));
+    let grouped = match *parent {
+        UnExpr(..) => groups_unary_operand(replacement),
+        BinExpr(_, op, _) if index == 0 => groups_left(op, replacement),
+        BinExpr(_, op, _) => groups_right(op, replacement),
+        Time | Num(_) => unreachable!("a leaf has no operands"),
+    };
+    if grouped {
+        format!("({})", text)
+    } else {
+        text
+    }
+}
+
+fn replace(expression: &Expr, path: &[usize], replacement: &Expr) -> Expr {
+    let (&index, rest) = match path.split_first() {
+        Some(first) => first,
+        None => return replacement.clone(),
+    };
+    match *expression {
+        UnExpr(op, ref expr) => UnExpr(op, Box::new(replace(expr, rest, replacement))),
+        BinExpr(ref expr1, op, ref expr2) if index == 0 => {
+            BinExpr(Box::new(replace(expr1, rest, replacement)), op, expr2.clone())
+        }
+        BinExpr(ref expr1, op, ref expr2) => {
+            BinExpr(expr1.clone(), op, Box::new(replace(expr2, rest, replacement)))
+        }
+        Time | Num(_) => unreachable!("the path leads to a node"),
+    }
+}
+
+/// The position of the node at `path` in `Expr::subexpressions`.
+fn preorder_index(root: &Expr, path: &[usize]) -> usize {
+    let mut index = 0;
+    let mut node = root;
+    for &i in path {
+        index += 1;
+        if i == 1 {
+            index += child(node, 0).node_count();
+        }
+        node = child(node, i);
+    }
+    index
+}
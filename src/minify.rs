@@ -0,0 +1,184 @@
+use canonical::is_commutative;
+use dag::{ExprDag, Node, NodeId};
+use equivalence::{Checker, Verdict};
+use expr::Expr;
+use numeral::Numeral::*;
+use ops::*;
+use optimize::optimize;
+use printer::{print, Style};
+use self::Expr::*;
+
+/// The shortest text we find that plays exactly like `expression`, checked
+/// with the default `Checker`.
+pub fn minify(expression: &Expr) -> String {
+    minify_with(expression, &Checker::default())
+}
+
+/// Like `minify`, comparing the result with `expression` using `checker`.
+/// If they differ at any `t`, this gives `expression` without whitespace
+/// instead.
+pub fn minify_with(expression: &Expr, checker: &Checker) -> String {
+    let golfed = golf(expression);
+    match checker.check(expression, &golfed) {
+        Verdict::Counterexample { .. } => print(expression, Style::Compact),
+        _ => print(&golfed, Style::Compact),
+    }
+}
+
+/// Rewrites `expression` into the tree with the shortest compact print we
+/// can find that evaluates the same at every `t`.
+///
+/// Constants are folded and identities stripped as `optimize` does, chains
+/// of `&`, `^` and `|` are grouped to the right, operands of commutative
+/// operators are swapped when that saves parentheses, `x+-5` becomes `x-5`,
+/// and literals are written as the shortest of `-10`, `~9` and `5<<20`.
+/// None of these can make a sub-expression fail or overflow where it
+/// didn't before.
+pub fn golf(expression: &Expr) -> Expr {
+    let dag = ExprDag::from(&optimize(expression));
+    let mut golfer = Golfer {
+        dag: &dag,
+        forms: vec![None; dag.nodes().len()],
+    };
+    shortest(golfer.forms(dag.root()))
+}
+
+/// The most forms kept for a sub-expression, and the most candidates a
+/// parent weighs, so the work per node is bounded whatever the input.
+const MAX_FORMS: usize = 3;
+const MAX_CANDIDATES: usize = 32;
+
+/// Finds the forms of each distinct sub-expression once, however often it
+/// appears and however many ways its parents are written.
+struct Golfer<'a> {
+    dag: &'a ExprDag,
+    forms: Vec<Option<Vec<Expr>>>,
+}
+
+impl<'a> Golfer<'a> {
+    /// The ways to write node `id`: every form of a literal, or the shortest
+    /// form of anything else. A parent picks among them, as a literal's
+    /// best form depends on whether it needs parentheses there.
+    fn forms(&mut self, id: NodeId) -> Vec<Expr> {
+        if let Some(ref forms) = self.forms[id] {
+            return forms.clone();
+        }
+
+        let mut forms = match self.dag.nodes()[id] {
+            Node::Time => vec![Time],
+            Node::Num(Int(i)) => literal_forms(i),
+            Node::Num(Float(f)) => literal_forms(f as i32),
+            Node::UnExpr(op, id) => {
+                let operands = self.forms(id);
+                vec![shortest(operands.into_iter().map(|operand| UnExpr(op, Box::new(operand))).collect())]
+            }
+            Node::BinExpr(_, op @ BinOp::Four(_), _) |
+            Node::BinExpr(_, op @ BinOp::Five(_), _) |
+            Node::BinExpr(_, op @ BinOp::Six(_), _) => vec![self.bitwise_chain(id, op)],
+            Node::BinExpr(id1, op, id2) => vec![self.binary(id1, op, id2)],
+        };
+        forms.truncate(MAX_FORMS);
+
+        self.forms[id] = Some(forms.clone());
+        forms
+    }
+
+    /// `&`, `^` and `|` never fail or overflow, so their chains can be
+    /// grouped to the right, which the parser reads without parentheses.
+    fn bitwise_chain(&mut self, id: NodeId, op: BinOp) -> Expr {
+        let mut operands = Vec::new();
+        self.chain(id, op, &mut operands);
+
+        let last = operands.pop().expect("a chain has two operands");
+        let mut right = self.forms(last);
+        for left in operands.into_iter().rev() {
+            let mut candidates = Vec::new();
+            combine(&self.forms(left), op, &right, &mut candidates);
+            right = vec![shortest(candidates)];
+        }
+        right.pop().expect("a chain has two operands")
+    }
+
+    /// Collects the operands of a chain of `op`, however it is grouped.
+    fn chain(&self, id: NodeId, op: BinOp, operands: &mut Vec<NodeId>) {
+        match self.dag.nodes()[id] {
+            Node::BinExpr(id1, o, id2) if o == op => {
+                self.chain(id1, op, operands);
+                self.chain(id2, op, operands);
+            }
+            _ => operands.push(id),
+        }
+    }
+
+    fn binary(&mut self, id1: NodeId, op: BinOp, id2: NodeId) -> Expr {
+        let forms1 = self.forms(id1);
+        let forms2 = self.forms(id2);
+
+        let mut candidates = Vec::new();
+        self.oriented(&forms1, op, &forms2, id2, &mut candidates);
+        if is_commutative(op) {
+            self.oriented(&forms2, op, &forms1, id1, &mut candidates);
+        }
+        shortest(candidates)
+    }
+
+    /// The ways to write `left op right`, where `right` holds the forms of
+    /// node `right_id`.
+    fn oriented(&self, lefts: &[Expr], op: BinOp, rights: &[Expr], right_id: NodeId, candidates: &mut Vec<Expr>) {
+        combine(lefts, op, rights, candidates);
+
+        // `x+-5` is `x-5`, and `x- -5` is `x+5`, overflowing for the same `x`
+        if let Node::Num(Int(i)) = self.dag.nodes()[right_id] {
+            let flipped = match op {
+                BinOp::Two(BinOp2::Add) => BinOp::Two(BinOp2::Sub),
+                BinOp::Two(BinOp2::Sub) => BinOp::Two(BinOp2::Add),
+                _ => return,
+            };
+            if i < 0 && i != i32::MIN {
+                combine(lefts, flipped, &literal_forms(-i), candidates);
+            }
+        }
+    }
+}
+
+fn literal_forms(i: i32) -> Vec<Expr> {
+    let mut forms = vec![Num(Int(i))];
+    if i < 0 {
+        forms.push(UnExpr(UnOp::BitNot, Box::new(Num(Int(!i)))));
+    }
+    let zeros = i.trailing_zeros();
+    if i != 0 && zeros > 1 {
+        // the sign bit is shifted out of `1`, which doesn't overflow
+        let base = if i == i32::MIN { 1 } else { i >> zeros };
+        forms.push(BinExpr(
+            Box::new(Num(Int(base))),
+            BinOp::Three(BitShift::Left),
+            Box::new(Num(Int(zeros as i32))),
+        ));
+    }
+    forms
+}
+
+fn combine(lefts: &[Expr], op: BinOp, rights: &[Expr], candidates: &mut Vec<Expr>) {
+    for left in lefts {
+        for right in rights {
+            if candidates.len() == MAX_CANDIDATES {
+                return;
+            }
+            candidates.push(BinExpr(Box::new(left.clone()), op, Box::new(right.clone())));
+        }
+    }
+}
+
+/// The candidate with the shortest compact print, the first one on a tie.
+fn shortest(candidates: Vec<Expr>) -> Expr {
+    let mut best: Option<(usize, Expr)> = None;
+    for candidate in candidates {
+        let length = print(&candidate, Style::Compact).len();
+        match best {
+            Some((shortest, _)) if shortest <= length => {}
+            _ => best = Some((length, candidate)),
+        }
+    }
+    best.expect("there is always a candidate").1
+}
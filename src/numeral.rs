@@ -25,7 +25,6 @@ named!(float<f32>,
            map_res!(
                recognize!(
                    tuple!(
-                       opt!(tag!("-")),
                        digit,
                        complete!(preceded!(tag!("."), opt!(digit)))
                    )
@@ -38,16 +37,12 @@ named!(float<f32>,
 
 named!(int<i32>,
        map_res!(
-           map_res!(
-               recognize!(
-                   tuple!(opt!(tag!("-")), digit)
-               ),
-               str::from_utf8
-           ),
-           |s| i32::from_str_radix(s, 10)
+           map_res!(recognize!(digit), str::from_utf8),
+           i32::from_str
        )
 );
 
+// Literals have no sign: `-5` is the `-` operator applied to `5`.
 named!(pub number<Numeral>,
        alt!(map!(float, Numeral::from) | map!(int, Numeral::from))
 );
@@ -1,4 +1,4 @@
-#[derive(Copy, Clone, Debug, PartialEq)]
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
 pub enum BinOp {
     One(BinOp1),
     Two(BinOp2),
@@ -8,40 +8,81 @@ pub enum BinOp {
     Six(BitOr),
 }
 
-#[derive(Copy, Clone, Debug, PartialEq)]
+impl BinOp {
+    /// The precedence level the operator belongs to, from 1 (`*` and `/`,
+    /// which bind tightest) to 6 (`|`).
+    pub fn level(&self) -> u8 {
+        match *self {
+            BinOp::One(_) => 1,
+            BinOp::Two(_) => 2,
+            BinOp::Three(_) => 3,
+            BinOp::Four(_) => 4,
+            BinOp::Five(_) => 5,
+            BinOp::Six(_) => 6,
+        }
+    }
+
+    /// The operator as it is written.
+    pub fn symbol(&self) -> &'static str {
+        match *self {
+            BinOp::One(BinOp1::Mul) => "*",
+            BinOp::One(BinOp1::Div) => "/",
+            BinOp::Two(BinOp2::Add) => "+",
+            BinOp::Two(BinOp2::Sub) => "-",
+            BinOp::Three(BitShift::Left) => "<<",
+            BinOp::Three(BitShift::Right) => ">>",
+            BinOp::Four(_) => "&",
+            BinOp::Five(_) => "^",
+            BinOp::Six(_) => "|",
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
 pub enum BinOp1 {
     Mul,
     Div,
 }
 
-#[derive(Copy, Clone, Debug, PartialEq)]
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
 pub enum BinOp2 {
     Sub,
     Add,
 }
 
-#[derive(Copy, Clone, Debug, PartialEq)]
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
 pub enum BitShift {
     Right,
     Left,
 }
 
-#[derive(Copy, Clone, Debug, PartialEq)]
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
 pub struct BitAnd;
 
-#[derive(Copy, Clone, Debug, PartialEq)]
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
 pub struct BitXOr;
 
-#[derive(Copy, Clone, Debug, PartialEq)]
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
 pub struct BitOr;
 
-#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
 pub enum UnOp {
     Neg,
     BoolNot,
     BitNot,
 }
 
+impl UnOp {
+    /// The operator as it is written.
+    pub fn symbol(&self) -> &'static str {
+        match *self {
+            UnOp::Neg => "-",
+            UnOp::BoolNot => "!",
+            UnOp::BitNot => "~",
+        }
+    }
+}
+
 named!(pub bit_and<BitAnd>, value!(BitAnd, tag!("&")));
 named!(pub bit_or<BitOr>, value!(BitOr, tag!("|")));
 named!(pub bit_xor<BitXOr>, value!(BitXOr, tag!("^")));
@@ -78,20 +119,9 @@ named!(pub binary_op<BinOp>,
        )
 );
 
-named!(neg_op<UnOp>,
-       value!(UnOp::Neg,
-              recognize!(
-                  tuple!(
-                      char!('-'),
-                      peek!(alt!(char!('(') | char!('t')))
-                  )
-              )
-       )
-);
-
 named!(pub unop<UnOp>,
        alt!(
-           neg_op |
+           value!(UnOp::Neg, char!('-')) |
            value!(UnOp::BoolNot, char!('!')) |
            value!(UnOp::BitNot, char!('~'))
        )
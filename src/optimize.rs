@@ -0,0 +1,124 @@
+use expr::Expr;
+use numeral::Numeral::*;
+use ops::*;
+use self::Expr::*;
+use self::BinOp::*;
+
+/// Folds constant sub-expressions and strips identity operations.
+///
+/// The result evaluates to exactly what `eval` gives for the original at
+/// every `t`. Sub-expressions that fail (`1/0`) or would overflow are left
+/// as they are so they still fail, or wrap, at playback time.
+pub fn optimize(expression: &Expr) -> Expr {
+    match *expression {
+        Time => Time,
+        Num(Int(i)) => Num(Int(i)),
+        Num(Float(f)) => Num(Int(f as i32)),
+        UnExpr(op, ref expr) => optimize_unop(op, optimize(expr)),
+        BinExpr(ref expr1, op, ref expr2) => {
+            optimize_binop(optimize(expr1), op, optimize(expr2))
+        }
+    }
+}
+
+fn optimize_unop(op: UnOp, expr: Expr) -> Expr {
+    match (op, expr) {
+        (UnOp::Neg, Num(Int(i))) if i != i32::MIN => Num(Int(-i)),
+        (UnOp::BoolNot, Num(Int(i))) | (UnOp::BitNot, Num(Int(i))) => Num(Int(!i)),
+        // `!` and `~` are both bitwise, so any pair of them cancels out
+        (UnOp::BoolNot, UnExpr(UnOp::BoolNot, inner)) |
+        (UnOp::BoolNot, UnExpr(UnOp::BitNot, inner)) |
+        (UnOp::BitNot, UnExpr(UnOp::BoolNot, inner)) |
+        (UnOp::BitNot, UnExpr(UnOp::BitNot, inner)) => *inner,
+        (op, expr) => UnExpr(op, Box::new(expr)),
+    }
+}
+
+fn optimize_binop(a: Expr, op: BinOp, b: Expr) -> Expr {
+    if let (&Num(Int(x)), &Num(Int(y))) = (&a, &b) {
+        if let Some(folded) = fold_binop(op, x, y) {
+            return Num(Int(folded));
+        }
+    }
+
+    match (op, literal(&a), literal(&b)) {
+        (Two(BinOp2::Add), _, Some(0)) |
+        (Two(BinOp2::Sub), _, Some(0)) |
+        (One(BinOp1::Mul), _, Some(1)) |
+        (One(BinOp1::Div), _, Some(1)) |
+        (Three(_), _, Some(0)) |
+        (Four(BitAnd), _, Some(-1)) |
+        (Five(BitXOr), _, Some(0)) |
+        (Six(BitOr), _, Some(0)) => return a,
+
+        (Two(BinOp2::Add), Some(0), _) |
+        (One(BinOp1::Mul), Some(1), _) |
+        (Four(BitAnd), Some(-1), _) |
+        (Five(BitXOr), Some(0), _) |
+        (Six(BitOr), Some(0), _) => return b,
+
+        (One(BinOp1::Mul), _, Some(0)) |
+        (Four(BitAnd), _, Some(0)) if is_total(&a) => return Num(Int(0)),
+        (One(BinOp1::Mul), Some(0), _) |
+        (Four(BitAnd), Some(0), _) if is_total(&b) => return Num(Int(0)),
+        (Six(BitOr), _, Some(-1)) if is_total(&a) => return Num(Int(-1)),
+        (Six(BitOr), Some(-1), _) if is_total(&b) => return Num(Int(-1)),
+
+        _ => {}
+    }
+
+    if a == b {
+        match op {
+            Four(BitAnd) | Six(BitOr) => return a,
+            Two(BinOp2::Sub) | Five(BitXOr) if is_total(&a) => return Num(Int(0)),
+            _ => {}
+        }
+    }
+
+    BinExpr(Box::new(a), op, Box::new(b))
+}
+
+fn literal(expression: &Expr) -> Option<i32> {
+    match *expression {
+        Num(Int(i)) => Some(i),
+        _ => None,
+    }
+}
+
+/// Applies `op` to two constants, or gives up if `eval` would fail, panic
+/// or wrap around.
+fn fold_binop(op: BinOp, a: i32, b: i32) -> Option<i32> {
+    match op {
+        Two(BinOp2::Add) => a.checked_add(b),
+        Two(BinOp2::Sub) => a.checked_sub(b),
+        One(BinOp1::Mul) => a.checked_mul(b),
+        One(BinOp1::Div) => a.checked_div(b),
+        Three(BitShift::Right) => if b < 0 { None } else { a.checked_shr(b as u32) },
+        Three(BitShift::Left) => if b < 0 { None } else { a.checked_shl(b as u32) },
+        Four(BitAnd) => Some(a & b),
+        Five(BitXOr) => Some(a ^ b),
+        Six(BitOr) => Some(a | b),
+    }
+}
+
+/// Whether evaluating `expression` can never fail or overflow, so it is safe
+/// to drop it from the tree entirely.
+fn is_total(expression: &Expr) -> bool {
+    match *expression {
+        Time | Num(_) => true,
+        UnExpr(UnOp::Neg, _) => false,
+        UnExpr(_, ref expr) => is_total(expr),
+        BinExpr(ref expr1, op, ref expr2) => {
+            let shift_in_range = match literal(expr2) {
+                Some(b) => (0..32).contains(&b),
+                None => false,
+            };
+            let op_is_total = match op {
+                Four(BitAnd) | Five(BitXOr) | Six(BitOr) => true,
+                Three(_) => shift_in_range,
+                _ => false,
+            };
+            op_is_total && is_total(expr1) && is_total(expr2)
+        }
+    }
+}
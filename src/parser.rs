@@ -1,256 +1,465 @@
+use std::fmt;
+
+use nom::IResult;
 
 use numeral::*;
 use ops::*;
 use expr::Expr;
+use limits::{self, Limits};
 use self::Expr::*;
 
+/// Expressions nested deeper than this are rejected, so that everything
+/// which walks a parsed tree recursively stays well clear of the stack limit.
+///
+/// Depth is counted in operators applied one to the result of another. A
+/// chain of one precedence level such as `t+t+…+t` is grouped to the right,
+/// so every operator in it counts, and a chain of more than `MAX_DEPTH`
+/// operators is rejected even without parentheses.
+pub const MAX_DEPTH: usize = 1024;
+
+/// Parses an expression.
+///
+/// Binary operators group to the right within a precedence level, and
+/// prefix operators bind tighter than any binary operator. Literals have
+/// no sign; a `-` is always a prefix operator where an operand is expected
+/// and a binary one after an operand, whatever the spacing, so `t -1` is
+/// `t - 1` and `-5`, `- 5`, `--t` and `-(-t)` all negate. As `2147483648`
+/// is too big for a literal, `-2147483648` can't be written. There are no
+/// comments; `parse_with_comments` reads them. Parsing uses an explicit
+/// stack rather than recursion, so any amount of nesting is either parsed
+/// or rejected without exhausting the call stack.
 pub fn parse(input: &str) -> Result<Expr, String> {
-    bit_or_exp(input.as_bytes()).to_result().map(Expr::from).map_err(
-        |e| {
-            format!("{}", e)
-        },
-    )
+    Parser::new(input.as_bytes(), false)
+        .parse()
+        .map(|parsed| parsed.expression)
+        .map_err(|failure| failure.to_string())
 }
 
-#[derive(Debug, PartialEq)]
-struct ShiftExp(Box<Exp>, Box<ShiftExp1>);
+/// A `//` comment, placed by how many literals and `t`s come before it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Comment {
+    /// The text after `//`, without the line break.
+    pub text: String,
+    /// The number of leaves (literals and `t`) before the comment. Leaves
+    /// are in the same order in the source and in the tree, so this places
+    /// the comment in the tree too.
+    pub leaves_before: usize,
+}
 
-#[derive(Debug, PartialEq)]
-enum ShiftExp1 {
-    Empty,
-    Seq(BitShift, Box<ShiftExp>),
+/// Parses an expression in which `//` starts a comment that runs to the
+/// end of the line, also returning its comments in source order. Without
+/// comments it accepts and gives the same as `parse`.
+pub fn parse_with_comments(input: &str) -> Result<(Expr, Vec<Comment>), String> {
+    match Parser::new(input.as_bytes(), true).parse() {
+        Ok(parsed) => Ok((parsed.expression, parsed.comments)),
+        Err(failure) => Err(failure.to_string()),
+    }
 }
 
-#[derive(Debug, PartialEq)]
-struct BitAndExp(Box<ShiftExp>, Box<BitAndExp1>);
+/// What could come next at some point of the input.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Expected {
+    /// `t`, a number, `(` or a unary operator.
+    Operand,
+    /// The second character of `<<` or `>>`.
+    Shift(BitShift),
+    /// A binary operator, or `)` if this many parentheses are open.
+    Operator { open_parens: usize },
+}
 
-#[derive(Debug, PartialEq)]
-enum BitAndExp1 {
-    Empty,
-    Seq(BitAnd, Box<BitAndExp>),
+/// Prints as the hint an editor could show, as in "`t`, a number, `(` or
+/// a unary operator".
+impl fmt::Display for Expected {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Expected::Operand => f.write_str("`t`, a number, `(` or a unary operator"),
+            Expected::Shift(BitShift::Left) => f.write_str("`<`"),
+            Expected::Shift(BitShift::Right) => f.write_str("`>`"),
+            Expected::Operator { open_parens: 0 } => f.write_str("a binary operator or the end"),
+            Expected::Operator { .. } => f.write_str("a binary operator or `)`"),
+        }
+    }
 }
 
-#[derive(Debug, PartialEq)]
-struct BitXOrExp(Box<BitAndExp>, Box<BitXOrExp1>);
+/// The result of `parse_partial`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Partial {
+    /// The input is a whole expression, though more could still be added
+    /// to it.
+    Complete(Expr),
+    /// The input is the start of an expression, but ends before it is
+    /// finished.
+    Incomplete { expected: Expected },
+    /// No expression starts like the input.
+    Invalid {
+        offset: usize,
+        /// What would have been valid at `offset`, if anything.
+        expected: Option<Expected>,
+        /// The error `parse` gives.
+        message: String,
+    },
+}
 
-#[derive(Debug, PartialEq)]
-enum BitXOrExp1 {
-    Empty,
-    Seq(BitXOr, Box<BitXOrExp>),
+/// Parses input that may still be being typed, telling input that only
+/// needs more added to it from input that is wrong, and what should come
+/// next.
+pub fn parse_partial(input: &str) -> Partial {
+    match Parser::new(input.as_bytes(), false).parse() {
+        Ok(parsed) => Partial::Complete(parsed.expression),
+        Err(Failure::Incomplete(expected)) => Partial::Incomplete { expected },
+        Err(Failure::Invalid { offset, expected, message }) => Partial::Invalid {
+            offset,
+            expected,
+            message,
+        },
+    }
+}
+
+/// A range of byte offsets into the source, `start` inclusive and `end`
+/// exclusive.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
 }
 
-#[derive(Debug, PartialEq)]
-struct BitOrExp(Box<BitXOrExp>, Box<BitOrExp1>);
+/// Parses an expression, also returning where each of its nodes is in the
+/// source, in the order of `Expr::subexpressions`. A node's span doesn't
+/// include parentheses around it.
+pub fn parse_with_spans(input: &str) -> Result<(Expr, Vec<Span>), String> {
+    let parsed = Parser::new(input.as_bytes(), false).parse().map_err(|failure| failure.to_string())?;
+    let spans = preorder(&parsed.expression, &parsed.spans);
+    Ok((parsed.expression, spans))
+}
 
-#[derive(Debug, PartialEq)]
-enum BitOrExp1 {
-    Empty,
-    Seq(BitOr, Box<BitOrExp>),
+/// Reorders spans given children first, as the parser finds them, to
+/// parents first.
+fn preorder(expression: &Expr, postorder: &[Span]) -> Vec<Span> {
+    let sizes = subtree_sizes(expression);
+    let mut spans = Vec::with_capacity(postorder.len());
+    // each node with the end of its sub-tree's spans in `postorder`
+    let mut stack = vec![(expression, postorder.len())];
+    while let Some((expression, end)) = stack.pop() {
+        spans.push(postorder[end - 1]);
+        match *expression {
+            Time | Num(_) => {}
+            UnExpr(_, ref expr) => stack.push((expr, end - 1)),
+            BinExpr(ref expr1, _, ref expr2) => {
+                // the right operand's sub-tree ends just before its parent
+                stack.push((expr2, end - 1));
+                stack.push((expr1, end - 1 - sizes[end - 2]));
+            }
+        }
+    }
+    spans
 }
 
-#[derive(Debug, PartialEq)]
-struct Exp(Box<Term>, Box<Exp1>);
+/// The number of nodes in the sub-tree of every node, children first.
+fn subtree_sizes(expression: &Expr) -> Vec<usize> {
+    let mut sizes: Vec<usize> = Vec::new();
+    let mut stack = vec![(expression, false)];
+    while let Some((expression, visited)) = stack.pop() {
+        if !visited {
+            stack.push((expression, true));
+            let children: Vec<_> = expression.children().collect();
+            stack.extend(children.into_iter().rev().map(|child| (child, false)));
+            continue;
+        }
 
-#[derive(Debug, PartialEq)]
-enum Exp1 {
-    Empty,
-    Seq(BinOp2, Box<Exp>),
+        let size = match *expression {
+            Time | Num(_) => 1,
+            UnExpr(..) => 1 + sizes[sizes.len() - 1],
+            BinExpr(..) => {
+                let right = sizes[sizes.len() - 1];
+                1 + right + sizes[sizes.len() - 1 - right]
+            }
+        };
+        sizes.push(size);
+    }
+    sizes
+}
+
+/// Parses untrusted input, rejecting it if it breaks any of `limits`.
+///
+/// The length of the source is checked before it is handed to the parser,
+/// so an oversized input is turned away without being parsed. Everything
+/// else is checked on the parsed tree.
+pub fn parse_with_limits(input: &str, limits: &Limits) -> Result<Expr, limits::Error> {
+    limits.check_source(input)?;
+    let expression = parse(input).map_err(limits::Error::Syntax)?;
+    limits.check_expr(&expression)?;
+    Ok(expression)
 }
 
-#[derive(Debug, PartialEq)]
-struct Term(Box<Factor>, Box<Term1>);
+named!(time<Expr>, value!(Time, alt!(char!('T') | char!('t'))));
+named!(num<Expr>, map!(number, Num));
+named!(operand<Expr>, alt!(time | num));
+
+/// Reads a literal or `t` at the start of `input`.
+pub(crate) fn leaf(input: &[u8]) -> IResult<&[u8], Expr> {
+    operand(input)
+}
 
-#[derive(Debug, PartialEq)]
-enum Term1 {
-    Empty,
-    Seq(BinOp1, Box<Term>),
+/// An operator waiting for its operands, with where it starts.
+enum Pending {
+    Paren(usize),
+    Unary(UnOp, usize),
+    Binary(BinOp),
 }
 
-#[derive(Debug, PartialEq)]
-enum Factor {
-    Expr(Box<Expr>),
-    Exp(Box<BitOrExp>),
-    Unary(UnOp, Box<Factor>),
+enum Failure {
+    Incomplete(Expected),
+    Invalid {
+        offset: usize,
+        expected: Option<Expected>,
+        message: String,
+    },
 }
 
-impl From<Factor> for Expr {
-    fn from(f: Factor) -> Self {
-        match f {
-            Factor::Expr(expr) => *expr,
-            Factor::Exp(exp) => Expr::from(*exp),
-            Factor::Unary(op, sub_f) => UnExpr(op, Box::new(Expr::from(*sub_f))),
+/// Prints the error `parse` gives.
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Failure::Incomplete(_) => f.write_str(&incomplete()),
+            Failure::Invalid { ref message, .. } => f.write_str(message),
         }
     }
 }
 
-impl From<Term> for Expr {
-    fn from(t: Term) -> Self {
-        match *t.1 {
-            Term1::Empty => Expr::from(*t.0),
-            Term1::Seq(op, t2) => {
-                BinExpr(
-                    Box::new(Expr::from(*t.0)),
-                    BinOp::One(op),
-                    Box::new(Expr::from(*t2)),
-                )
-            }
+struct Parsed {
+    expression: Expr,
+    comments: Vec<Comment>,
+    /// The span of every node, children first.
+    spans: Vec<Span>,
+}
+
+struct Parser<'a> {
+    input: &'a [u8],
+    rest: &'a [u8],
+    /// Finished sub-expressions, with their depth and their span including
+    /// any parentheses around them.
+    operands: Vec<(Expr, usize, Span)>,
+    operators: Vec<Pending>,
+    /// `None` if `//` doesn't start a comment.
+    comments: Option<Vec<Comment>>,
+    leaves: usize,
+    spans: Vec<Span>,
+}
+
+impl<'a> Parser<'a> {
+    fn new(input: &'a [u8], comments: bool) -> Self {
+        Parser {
+            input,
+            rest: input,
+            operands: Vec::new(),
+            operators: Vec::new(),
+            comments: if comments { Some(Vec::new()) } else { None },
+            leaves: 0,
+            spans: Vec::new(),
         }
     }
-}
 
-impl From<Exp> for Expr {
-    fn from(e: Exp) -> Self {
-        match *e.1 {
-            Exp1::Empty => Expr::from(*e.0),
-            Exp1::Seq(op, e2) => {
-                BinExpr(
-                    Box::new(Expr::from(*e.0)),
-                    BinOp::Two(op),
-                    Box::new(Expr::from(*e2)),
-                )
+    fn parse(mut self) -> Result<Parsed, Failure> {
+        loop {
+            self.parse_operand()?;
+            if !self.parse_operator()? {
+                break;
+            }
+        }
+
+        let open_parens = self.open_parens();
+        while let Some(pending) = self.operators.pop() {
+            match pending {
+                Pending::Binary(op) => self.reduce(op)?,
+                Pending::Paren(_) | Pending::Unary(..) => {
+                    return Err(Failure::Incomplete(Expected::Operator { open_parens }))
+                }
             }
         }
+
+        let (expression, _, _) = self.operands.pop().expect("parsed an operand");
+        Ok(Parsed {
+            expression,
+            comments: self.comments.unwrap_or_default(),
+            spans: self.spans,
+        })
     }
-}
 
-impl From<ShiftExp> for Expr {
-    fn from(e: ShiftExp) -> Self {
-        match *e.1 {
-            ShiftExp1::Empty => Expr::from(*e.0),
-            ShiftExp1::Seq(op, e2) => {
-                BinExpr(
-                    Box::new(Expr::from(*e.0)),
-                    BinOp::Three(op),
-                    Box::new(Expr::from(*e2)),
-                )
+    /// Reads prefix operators and open parentheses up to and including the
+    /// next literal or `t`.
+    fn parse_operand(&mut self) -> Result<(), Failure> {
+        loop {
+            self.skip_whitespace();
+            if self.rest.is_empty() {
+                return Err(Failure::Incomplete(Expected::Operand));
+            }
+
+            let start = self.offset();
+            if let IResult::Done(rest, expression) = operand(self.rest) {
+                self.rest = rest;
+                self.leaves += 1;
+                let span = Span { start, end: self.offset() };
+                self.spans.push(span);
+                return self.push_operand(expression, 1, span);
+            }
+
+            if self.rest[0] == b'(' {
+                self.rest = &self.rest[1..];
+                self.operators.push(Pending::Paren(start));
+                continue;
+            }
+
+            match unop(self.rest) {
+                IResult::Done(rest, op) => {
+                    self.rest = rest;
+                    self.operators.push(Pending::Unary(op, start));
+                }
+                IResult::Incomplete(_) | IResult::Error(_) => {
+                    let message = format!("expected {} at offset {}", Expected::Operand, self.offset());
+                    return Err(self.invalid(Some(Expected::Operand), message));
+                }
             }
         }
     }
-}
 
-impl From<BitAndExp> for Expr {
-    fn from(e: BitAndExp) -> Self {
-        match *e.1 {
-            BitAndExp1::Empty => Expr::from(*e.0),
-            BitAndExp1::Seq(op, e2) => {
-                BinExpr(
-                    Box::new(Expr::from(*e.0)),
-                    BinOp::Four(op),
-                    Box::new(Expr::from(*e2)),
-                )
+    /// Reads closing parentheses up to and including the next binary
+    /// operator. Returns false at the end of the input.
+    fn parse_operator(&mut self) -> Result<bool, Failure> {
+        loop {
+            self.skip_whitespace();
+            if self.rest.is_empty() {
+                return Ok(false);
+            }
+
+            if self.rest[0] == b')' {
+                self.close_paren()?;
+                self.rest = &self.rest[1..];
+                continue;
+            }
+
+            match binary_op(self.rest) {
+                IResult::Done(rest, op) => {
+                    self.rest = rest;
+                    // equal levels stay on the stack, which groups them to the right
+                    while let Some(&Pending::Binary(top)) = self.operators.last() {
+                        if top.level() >= op.level() {
+                            break;
+                        }
+                        self.operators.pop();
+                        self.reduce(top)?;
+                    }
+                    self.operators.push(Pending::Binary(op));
+                    return Ok(true);
+                }
+                // only the first character of a shift can end the input
+                IResult::Incomplete(_) => {
+                    let shift = match self.rest[0] {
+                        b'<' => BitShift::Left,
+                        _ => BitShift::Right,
+                    };
+                    return Err(Failure::Incomplete(Expected::Shift(shift)));
+                }
+                IResult::Error(_) => {
+                    let expected = Expected::Operator { open_parens: self.open_parens() };
+                    let message = format!("unexpected input at offset {}", self.offset());
+                    return Err(self.invalid(Some(expected), message));
+                }
             }
         }
     }
-}
 
-impl From<BitXOrExp> for Expr {
-    fn from(e: BitXOrExp) -> Self {
-        match *e.1 {
-            BitXOrExp1::Empty => Expr::from(*e.0),
-            BitXOrExp1::Seq(op, e2) => {
-                BinExpr(
-                    Box::new(Expr::from(*e.0)),
-                    BinOp::Five(op),
-                    Box::new(Expr::from(*e2)),
-                )
+    fn close_paren(&mut self) -> Result<(), Failure> {
+        let start = loop {
+            match self.operators.pop() {
+                Some(Pending::Binary(op)) => self.reduce(op)?,
+                Some(Pending::Paren(start)) => break start,
+                Some(Pending::Unary(..)) => unreachable!("unary operators are applied eagerly"),
+                None => {
+                    let expected = Expected::Operator { open_parens: 0 };
+                    let message = format!("unmatched `)` at offset {}", self.offset());
+                    return Err(self.invalid(Some(expected), message));
+                }
             }
+        };
+
+        // the parenthesised expression is an operand of any prefix operators before it
+        let (expression, depth, _) = self.operands.pop().expect("parsed an operand");
+        let span = Span { start, end: self.offset() + 1 };
+        self.push_operand(expression, depth, span)
+    }
+
+    fn push_operand(&mut self, mut expression: Expr, mut depth: usize, mut span: Span) -> Result<(), Failure> {
+        while let Some(&Pending::Unary(op, start)) = self.operators.last() {
+            self.operators.pop();
+            expression = UnExpr(op, Box::new(expression));
+            depth = check_depth(depth + 1).map_err(|message| self.invalid(None, message))?;
+            span.start = start;
+            self.spans.push(span);
         }
+        self.operands.push((expression, depth, span));
+        Ok(())
+    }
+
+    fn reduce(&mut self, op: BinOp) -> Result<(), Failure> {
+        let (b, depth_b, span_b) = self.operands.pop().expect("parsed an operand");
+        let (a, depth_a, span_a) = self.operands.pop().expect("parsed an operand");
+        let depth = check_depth(1 + depth_a.max(depth_b)).map_err(|message| self.invalid(None, message))?;
+        let span = Span { start: span_a.start, end: span_b.end };
+        self.spans.push(span);
+        self.operands.push((BinExpr(Box::new(a), op, Box::new(b)), depth, span));
+        Ok(())
     }
-}
 
-impl From<BitOrExp> for Expr {
-    fn from(e: BitOrExp) -> Self {
-        match *e.1 {
-            BitOrExp1::Empty => Expr::from(*e.0),
-            BitOrExp1::Seq(op, e2) => {
-                BinExpr(
-                    Box::new(Expr::from(*e.0)),
-                    BinOp::Six(op),
-                    Box::new(Expr::from(*e2)),
-                )
+    /// Skips whitespace, and comments if they are read.
+    fn skip_whitespace(&mut self) {
+        while let Some(&c) = self.rest.first() {
+            match (c, self.comments.as_mut()) {
+                (b' ', _) | (b'\t', _) | (b'\r', _) | (b'\n', _) => self.rest = &self.rest[1..],
+                (b'/', Some(comments)) if self.rest.get(1) == Some(&b'/') => {
+                    let end = self.rest.iter().position(|&c| c == b'\n').unwrap_or(self.rest.len());
+                    let text = String::from_utf8_lossy(&self.rest[2..end]);
+                    comments.push(Comment {
+                        text: text.trim_end_matches('\r').to_owned(),
+                        leaves_before: self.leaves,
+                    });
+                    self.rest = &self.rest[end..];
+                }
+                _ => break,
             }
         }
     }
+
+    fn offset(&self) -> usize {
+        self.input.len() - self.rest.len()
+    }
+
+    fn open_parens(&self) -> usize {
+        self.operators
+            .iter()
+            .filter(|pending| matches!(**pending, Pending::Paren(_)))
+            .count()
+    }
+
+    fn invalid(&self, expected: Option<Expected>, message: String) -> Failure {
+        Failure::Invalid {
+            offset: self.offset(),
+            expected,
+            message,
+        }
+    }
 }
 
-named!(time<Expr>, value!(Time, alt!(char!('T') | char!('t'))));
-named!(num<Expr>, map!(number, Num));
+pub(crate) fn check_depth(depth: usize) -> Result<usize, String> {
+    if depth > MAX_DEPTH {
+        Err(format!(
+            "expression is more than {} operators deep, counting each operator of a chain such as `t+t+t`",
+            MAX_DEPTH
+        ))
+    } else {
+        Ok(depth)
+    }
+}
 
-named!(bit_or_exp<BitOrExp>, ws!(
-    map!(pair!(bit_xor_exp, bit_or_exp1), |(e, se)| BitOrExp(Box::new(e), Box::new(se)))
-));
-
-named!(bit_or_exp1<BitOrExp1>, ws!(alt!(
-    value!(BitOrExp1::Empty, empty) |
-    map!(pair!(bit_or, bit_or_exp), |(o, e)| BitOrExp1::Seq(o, Box::new(e))) |
-    value!(BitOrExp1::Empty, tag!(""))
-)));
-
-named!(bit_xor_exp<BitXOrExp>, ws!(
-    map!(pair!(bit_and_exp, bit_xor_exp1), |(e, se)| BitXOrExp(Box::new(e), Box::new(se)))
-));
-
-named!(bit_xor_exp1<BitXOrExp1>, ws!(alt!(
-    value!(BitXOrExp1::Empty, empty) |
-    map!(pair!(bit_xor, bit_xor_exp), |(o, e)| BitXOrExp1::Seq(o, Box::new(e))) |
-    value!(BitXOrExp1::Empty, tag!(""))
-)));
-
-named!(bit_and_exp<BitAndExp>, ws!(
-    map!(pair!(shift_exp, bit_and_exp1), |(e, se)| BitAndExp(Box::new(e), Box::new(se)))
-));
-
-named!(bit_and_exp1<BitAndExp1>, ws!(alt!(
-    value!(BitAndExp1::Empty, empty) |
-    map!(pair!(bit_and, bit_and_exp), |(o, e)| BitAndExp1::Seq(o, Box::new(e))) |
-    value!(BitAndExp1::Empty, tag!(""))
-)));
-
-named!(shift_exp<ShiftExp>, ws!(
-    map!(pair!(exp, shift_exp1), |(e, se)| ShiftExp(Box::new(e), Box::new(se)))
-));
-
-named!(shift_exp1<ShiftExp1>, ws!(alt!(
-    value!(ShiftExp1::Empty, empty) |
-    map!(pair!(bit_shift, shift_exp), |(o, e)| ShiftExp1::Seq(o, Box::new(e))) |
-    value!(ShiftExp1::Empty, tag!(""))
-)));
-
-named!(exp<Exp>, ws!(
-    map!(pair!(term, exp1), |(t, e)| Exp(Box::new(t), Box::new(e)))
-));
-
-named!(exp1<Exp1>, ws!(alt!(
-    value!(Exp1::Empty, empty) |
-    map!(pair!(add_or_sub, exp), |(o, e)| Exp1::Seq(o, Box::new(e))) |
-    value!(Exp1::Empty, tag!(""))
-)));
-
-named!(term<Term>, ws!(
-    map!(pair!(factor, term1), |(f, t)| Term(Box::new(f), Box::new(t)))
-));
-
-named!(empty<&[u8]>, alt!(eof!()));
-
-named!(term1<Term1>, ws!(alt!(
-    value!(Term1::Empty, empty) |
-    map!(pair!(mul_or_div, term), |(o, t)| Term1::Seq(o, Box::new(t))) |
-    value!(Term1::Empty, tag!(""))
-)));
-
-named!(factor<Factor>, ws!(
-    alt!(
-        map!(
-            alt!(time | num),
-            |l| Factor::Expr(Box::new(l))
-        ) |
-        map!(
-            delimited!(char!('('), bit_or_exp, char!(')')),
-            |e| Factor::Exp(Box::new(e))
-        ) |
-        map!(
-            pair!(unop, factor),
-            |(o, f)| Factor::Unary(o, Box::new(f))
-        )
-    )
-));
+pub(crate) fn incomplete() -> String {
+    "incomplete expression".to_owned()
+}
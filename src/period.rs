@@ -0,0 +1,91 @@
+use bits::influence;
+use eval::{eval_block, EvalContext};
+use expr::Expr;
+
+/// The smallest period of an expression's output, in samples.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Period {
+    /// Follows from the bit structure of the expression, so the output
+    /// repeats forever.
+    Exact(usize),
+    /// Seen in the rendered output, which repeated at least twice, but not
+    /// proven to hold beyond it.
+    Observed(usize),
+}
+
+/// Finds the smallest period of the output of `expression`, as rendered by
+/// an `ExprSignal` from `t = 0`, as long as it is at most `bound` samples.
+///
+/// An exact answer is given when the output only depends on the low bits
+/// of `t`. Otherwise `2 * bound` samples are rendered and searched for a
+/// repeating pattern.
+pub fn period(expression: &Expr, bound: usize) -> Option<Period> {
+    if let Some(upper) = analytic_period(expression) {
+        if upper <= bound as u64 {
+            let samples = render(expression, upper as usize);
+            return Some(Period::Exact(cyclic_period(&samples)));
+        }
+    }
+
+    let samples = render(expression, bound.saturating_mul(2));
+    observed_period(&samples)
+        .filter(|&p| p <= bound)
+        .map(Period::Observed)
+}
+
+/// A power of two the output is guaranteed to repeat after, found from
+/// which bits of `t` the low 8 bits of the output (the part that ends up in
+/// a sample) can depend on. `None` if the high bits of `t` matter.
+pub fn analytic_period(expression: &Expr) -> Option<u64> {
+    let used = influence(expression).sample();
+    if used >> 31 != 0 {
+        return None;
+    }
+    Some(1 << (32 - used.leading_zeros()))
+}
+
+/// The smallest `p` such that `samples` is made of repeats of its first `p`
+/// samples (the last one possibly cut short), if it repeats at least once.
+pub fn observed_period<T: PartialEq>(samples: &[T]) -> Option<usize> {
+    if samples.is_empty() {
+        return None;
+    }
+
+    // the longest proper prefix that is also a suffix leaves the period
+    let mut border = vec![0; samples.len()];
+    for i in 1..samples.len() {
+        let mut k = border[i - 1];
+        while k > 0 && samples[i] != samples[k] {
+            k = border[k - 1];
+        }
+        if samples[i] == samples[k] {
+            k += 1;
+        }
+        border[i] = k;
+    }
+
+    let p = samples.len() - border[samples.len() - 1];
+    if 2 * p <= samples.len() {
+        Some(p)
+    } else {
+        None
+    }
+}
+
+/// The first `len` samples, as `ExprSignal::fill` renders them.
+fn render(expression: &Expr, len: usize) -> Vec<i8> {
+    let mut out = vec![0; len];
+    eval_block(&mut EvalContext::new(expression.clone()), 0, &mut out);
+    out.iter().map(|&x| x as i8).collect()
+}
+
+/// The smallest period of `samples` repeated end to end. `samples.len()`
+/// must be a power of two, so every period is one too.
+fn cyclic_period(samples: &[i8]) -> usize {
+    let n = samples.len();
+    let mut p = 1;
+    while p < n && (0..n).any(|i| samples[i] != samples[(i + p) % n]) {
+        p *= 2;
+    }
+    p
+}
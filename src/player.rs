@@ -5,7 +5,7 @@ use std::sync::{Arc, Mutex};
 use pa;
 
 use signal::ExprSignal;
-use sample::Signal;
+use sample::slice::to_frame_slice_mut;
 
 pub struct Player {
     pa: pa::PortAudio,
@@ -56,8 +56,8 @@ impl Player {
         let callback = move |pa::OutputStreamCallbackArgs { buffer, .. }| {
             stream
                 .lock()
-                .map(|mut stream| for output_sample in buffer.iter_mut() {
-                    *output_sample = stream.next()[0];
+                .map(|mut stream| if let Some(frames) = to_frame_slice_mut(buffer) {
+                    stream.fill(frames);
                 })
                 .ok();
 
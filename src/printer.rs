@@ -0,0 +1,109 @@
+use std::fmt;
+
+use expr::Expr;
+use numeral::Numeral::*;
+use ops::*;
+use self::Expr::*;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Style {
+    /// No spaces: `t*5&t>>7`.
+    Compact,
+    /// A space on each side of every binary operator: `t * 5 & t >> 7`.
+    Spaced,
+}
+
+/// Prints `expression` with only the parentheses the parser needs to build
+/// the same tree back: `parse(&print(e, style)) == Ok(e)` for any `e` the
+/// parser can produce.
+///
+/// The parser never produces a negative literal, but folding constants
+/// does. One prints as `-` and its magnitude, which reads back as a
+/// negation with the same value, and `i32::MIN`, whose magnitude is too big
+/// for a literal, prints as `~2147483647`.
+pub fn print(expression: &Expr, style: Style) -> String {
+    let mut out = String::new();
+    write(expression, style, &mut out);
+    out
+}
+
+/// Prints in the compact style, or the spaced one with `{:#}`.
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let style = if f.alternate() {
+            Style::Spaced
+        } else {
+            Style::Compact
+        };
+        f.write_str(&print(self, style))
+    }
+}
+
+fn write(expression: &Expr, style: Style, out: &mut String) {
+    match *expression {
+        Time => out.push('t'),
+        Num(Int(i32::MIN)) => out.push_str("~2147483647"),
+        Num(Int(i)) => out.push_str(&i.to_string()),
+        Num(Float(f)) => {
+            let text = f.to_string();
+            out.push_str(&text);
+            // without a point it would parse as an integer
+            if !text.contains('.') {
+                out.push_str(".0");
+            }
+        }
+        UnExpr(op, ref expr) => {
+            out.push_str(op.symbol());
+            write_grouped(expr, groups_unary_operand(expr), style, out);
+        }
+        BinExpr(ref expr1, op, ref expr2) => {
+            write_grouped(expr1, groups_left(op, expr1), style, out);
+            if style == Style::Spaced {
+                out.push(' ');
+                out.push_str(op.symbol());
+                out.push(' ');
+            } else {
+                out.push_str(op.symbol());
+            }
+            write_grouped(expr2, groups_right(op, expr2), style, out);
+        }
+    }
+}
+
+fn write_grouped(expression: &Expr, grouped: bool, style: Style, out: &mut String) {
+    if grouped {
+        out.push('(');
+        write(expression, style, out);
+        out.push(')');
+    } else {
+        write(expression, style, out);
+    }
+}
+
+/// Whether the operand of a prefix operator needs parentheses.
+pub(crate) fn groups_unary_operand(operand: &Expr) -> bool {
+    match *operand {
+        Time | Num(_) | UnExpr(..) => false,
+        BinExpr(..) => true,
+    }
+}
+
+/// Whether the left operand of `op` needs parentheses. Operators of one
+/// level group to the right, so only a left operand needs them at the same
+/// level.
+pub(crate) fn groups_left(op: BinOp, operand: &Expr) -> bool {
+    level(operand).is_some_and(|level| level >= op.level())
+}
+
+pub(crate) fn groups_right(op: BinOp, operand: &Expr) -> bool {
+    level(operand).is_some_and(|level| level > op.level())
+}
+
+/// The precedence level of a binary operation. Anything else binds tighter
+/// than every binary operator.
+fn level(expression: &Expr) -> Option<u8> {
+    match *expression {
+        BinExpr(_, op, _) => Some(op.level()),
+        _ => None,
+    }
+}
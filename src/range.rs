@@ -0,0 +1,388 @@
+use std::cmp;
+
+use expr::Expr;
+use numeral::Numeral::*;
+use ops::*;
+use self::Expr::*;
+use self::BinOp::*;
+
+/// An inclusive range of `i32` values.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Interval {
+    pub lo: i32,
+    pub hi: i32,
+}
+
+impl Interval {
+    pub fn new(lo: i32, hi: i32) -> Self {
+        assert!(lo <= hi, "empty interval");
+        Interval { lo, hi }
+    }
+
+    pub fn full() -> Self {
+        Interval::new(i32::MIN, i32::MAX)
+    }
+
+    pub fn contains(&self, x: i32) -> bool {
+        (self.lo..=self.hi).contains(&x)
+    }
+
+    /// Bits that have the same value for every number in the interval.
+    fn known_bits(&self) -> KnownBits {
+        if (self.lo < 0) != (self.hi < 0) {
+            return KnownBits::default();
+        }
+        // within one sign, signed order matches the order of the bit patterns
+        let (lo, hi) = (self.lo as u32, self.hi as u32);
+        let prefix = match (lo ^ hi).leading_zeros() {
+            32 => !0,
+            n => !(!0u32 >> n),
+        };
+        KnownBits {
+            zeros: !lo & prefix,
+            ones: lo & prefix,
+        }
+    }
+
+    fn clamp(lo: i64, hi: i64) -> Option<Interval> {
+        if lo < i64::from(i32::MIN) || hi > i64::from(i32::MAX) {
+            None
+        } else {
+            Some(Interval::new(lo as i32, hi as i32))
+        }
+    }
+}
+
+/// Bits known to be 0 or 1 in every value an expression takes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct KnownBits {
+    pub zeros: u32,
+    pub ones: u32,
+}
+
+impl KnownBits {
+    /// The smallest and largest numbers with these bits.
+    fn bounds(&self) -> Interval {
+        const SIGN: u32 = 1 << 31;
+        let unknown = !(self.zeros | self.ones);
+        let (lo, hi) = if unknown & SIGN != 0 {
+            (self.ones | SIGN, !self.zeros & !SIGN)
+        } else {
+            (self.ones, !self.zeros)
+        };
+        Interval::new(lo as i32, hi as i32)
+    }
+}
+
+/// What is known about the values of an expression.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Value {
+    pub range: Interval,
+    pub bits: KnownBits,
+}
+
+impl Value {
+    fn exact(x: i32) -> Self {
+        Value::from(Interval::new(x, x))
+    }
+
+    fn from_bits(bits: KnownBits) -> Self {
+        Value::from(bits.bounds()).with_bits(bits)
+    }
+
+    fn with_bits(self, bits: KnownBits) -> Self {
+        let bits = KnownBits {
+            zeros: self.bits.zeros | bits.zeros,
+            ones: self.bits.ones | bits.ones,
+        };
+        let bounds = bits.bounds();
+        let range = Interval::new(
+            cmp::max(self.range.lo, bounds.lo),
+            cmp::min(self.range.hi, bounds.hi),
+        );
+        Value {
+            range,
+            bits: KnownBits {
+                zeros: bits.zeros | range.known_bits().zeros,
+                ones: bits.ones | range.known_bits().ones,
+            },
+        }
+    }
+}
+
+impl From<Interval> for Value {
+    fn from(range: Interval) -> Self {
+        Value {
+            range,
+            bits: range.known_bits(),
+        }
+    }
+}
+
+/// The result of `analyze`. Nodes are identified by their path from the
+/// root: the index of the operand taken at each step, 0 for the left (or
+/// only) operand and 1 for the right.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Report {
+    /// What is known about the output, or `None` if evaluating the
+    /// expression fails for every `t` in range.
+    pub output: Option<Value>,
+    /// Operators whose result may not fit in an `i32`, so it wraps around
+    /// (or panics, in a debug build).
+    pub overflows: Vec<Vec<usize>>,
+    /// Divisions whose divisor may be 0.
+    pub divisions_by_zero: Vec<Vec<usize>>,
+}
+
+impl Report {
+    /// Whether the output is the same for every `t`, i.e. the expression is
+    /// silent.
+    pub fn is_constant(&self) -> bool {
+        match self.output {
+            Some(value) => value.range.lo == value.range.hi,
+            None => true,
+        }
+    }
+
+    /// Whether every output fits in the 8-bit samples the signal produces
+    /// without wrapping around.
+    pub fn fits_sample(&self) -> bool {
+        match self.output {
+            Some(value) => {
+                value.range.lo >= i32::from(i8::MIN) && value.range.hi <= i32::from(i8::MAX)
+            }
+            None => true,
+        }
+    }
+}
+
+/// Bounds the output of `expression` for every `t` in `time` by abstract
+/// interpretation over intervals and known bits, and finds the operators
+/// that may overflow or divide by zero on the way.
+///
+/// The bounds are sound but not always tight.
+pub fn analyze(expression: &Expr, time: Interval) -> Report {
+    let mut analysis = Analysis {
+        time: Value::from(time),
+        path: Vec::new(),
+        report: Report {
+            output: None,
+            overflows: Vec::new(),
+            divisions_by_zero: Vec::new(),
+        },
+    };
+    analysis.report.output = analysis.value(expression);
+    analysis.report
+}
+
+struct Analysis {
+    time: Value,
+    path: Vec<usize>,
+    report: Report,
+}
+
+impl Analysis {
+    fn value(&mut self, expression: &Expr) -> Option<Value> {
+        match *expression {
+            Time => Some(self.time),
+            Num(Int(i)) => Some(Value::exact(i)),
+            Num(Float(f)) => Some(Value::exact(f as i32)),
+            UnExpr(op, ref expr) => {
+                self.path.push(0);
+                let x = self.value(expr);
+                self.path.pop();
+                x.map(|x| self.unop(op, x))
+            }
+            BinExpr(ref expr1, op, ref expr2) => {
+                self.path.push(0);
+                let a = self.value(expr1);
+                self.path.pop();
+                self.path.push(1);
+                let b = self.value(expr2);
+                self.path.pop();
+                match (a, b) {
+                    (Some(a), Some(b)) => self.binop(op, a, b),
+                    _ => None,
+                }
+            }
+        }
+    }
+
+    fn unop(&mut self, op: UnOp, x: Value) -> Value {
+        match op {
+            UnOp::Neg => {
+                let range = Interval::clamp(-i64::from(x.range.hi), -i64::from(x.range.lo));
+                self.arithmetic(range)
+            }
+            UnOp::BoolNot | UnOp::BitNot => Value::from(Interval::new(!x.range.hi, !x.range.lo))
+                .with_bits(KnownBits {
+                    zeros: x.bits.ones,
+                    ones: x.bits.zeros,
+                }),
+        }
+    }
+
+    fn binop(&mut self, op: BinOp, a: Value, b: Value) -> Option<Value> {
+        let (a_lo, a_hi) = (i64::from(a.range.lo), i64::from(a.range.hi));
+        let (b_lo, b_hi) = (i64::from(b.range.lo), i64::from(b.range.hi));
+
+        let value = match op {
+            Two(BinOp2::Add) => self
+                .arithmetic(Interval::clamp(a_lo + b_lo, a_hi + b_hi))
+                .with_bits(low_bits(a.bits, b.bits, u32::wrapping_add)),
+            Two(BinOp2::Sub) => self
+                .arithmetic(Interval::clamp(a_lo - b_hi, a_hi - b_lo))
+                .with_bits(low_bits(a.bits, b.bits, u32::wrapping_sub)),
+            One(BinOp1::Mul) => {
+                let corners = [a_lo * b_lo, a_lo * b_hi, a_hi * b_lo, a_hi * b_hi];
+                let trailing_zeros = cmp::min(32, trailing_zeros(a.bits) + trailing_zeros(b.bits));
+                self.arithmetic(hull(&corners))
+                    .with_bits(low_bits(a.bits, b.bits, u32::wrapping_mul))
+                    .with_bits(KnownBits {
+                        zeros: low_mask(trailing_zeros),
+                        ones: 0,
+                    })
+            }
+            One(BinOp1::Div) => return self.div(a, b),
+            Three(shift) => self.shift(shift, a, b),
+            Four(BitAnd) => Value::from_bits(KnownBits {
+                zeros: a.bits.zeros | b.bits.zeros,
+                ones: a.bits.ones & b.bits.ones,
+            }),
+            Five(BitXOr) => Value::from_bits(KnownBits {
+                zeros: (a.bits.zeros & b.bits.zeros) | (a.bits.ones & b.bits.ones),
+                ones: (a.bits.zeros & b.bits.ones) | (a.bits.ones & b.bits.zeros),
+            }),
+            Six(BitOr) => Value::from_bits(KnownBits {
+                zeros: a.bits.zeros & b.bits.zeros,
+                ones: a.bits.ones | b.bits.ones,
+            }),
+        };
+        Some(value)
+    }
+
+    /// Either the exact result range, or a recorded overflow.
+    fn arithmetic(&mut self, range: Option<Interval>) -> Value {
+        match range {
+            Some(range) => Value::from(range),
+            None => {
+                self.report.overflows.push(self.path.clone());
+                Value::from(Interval::full())
+            }
+        }
+    }
+
+    fn div(&mut self, a: Value, b: Value) -> Option<Value> {
+        if b.range.contains(0) {
+            self.report.divisions_by_zero.push(self.path.clone());
+        }
+        if a.range.contains(i32::MIN) && b.range.contains(-1) {
+            self.report.overflows.push(self.path.clone());
+        }
+
+        // truncating division is monotone in each operand on either side of
+        // 0, so the extremes are at the corners
+        let (a_lo, a_hi) = (i64::from(a.range.lo), i64::from(a.range.hi));
+        let mut quotients = Vec::new();
+        for &(lo, hi) in [(b.range.lo, cmp::min(b.range.hi, -1)), (cmp::max(b.range.lo, 1), b.range.hi)].iter() {
+            if lo <= hi {
+                for &d in [i64::from(lo), i64::from(hi)].iter() {
+                    quotients.push(a_lo / d);
+                    quotients.push(a_hi / d);
+                }
+            }
+        }
+
+        if quotients.is_empty() {
+            return None;
+        }
+        // `i32::MIN / -1` is the one quotient that doesn't fit
+        Some(Value::from(hull(&quotients).unwrap_or_else(Interval::full)))
+    }
+
+    fn shift(&mut self, shift: BitShift, a: Value, b: Value) -> Value {
+        if b.range.lo < 0 || b.range.hi > 31 {
+            self.report.overflows.push(self.path.clone());
+            return Value::from(Interval::full());
+        }
+
+        let (b_lo, b_hi) = (b.range.lo as u32, b.range.hi as u32);
+        match shift {
+            BitShift::Right => {
+                // monotone in each operand, so the extremes are at the corners
+                let corners = [
+                    i64::from(a.range.lo >> b_lo),
+                    i64::from(a.range.lo >> b_hi),
+                    i64::from(a.range.hi >> b_lo),
+                    i64::from(a.range.hi >> b_hi),
+                ];
+                let value = Value::from(hull(&corners).unwrap());
+                if b_lo == b_hi {
+                    let sign_zero = a.bits.zeros >> 31 == 1;
+                    let sign_one = a.bits.ones >> 31 == 1;
+                    let high = !low_mask(32 - b_lo);
+                    value.with_bits(KnownBits {
+                        zeros: (a.bits.zeros >> b_lo) | if sign_zero { high } else { 0 },
+                        ones: (a.bits.ones >> b_lo) | if sign_one { high } else { 0 },
+                    })
+                } else {
+                    value
+                }
+            }
+            BitShift::Left => {
+                // bits shifted past the top are lost, so only the bit
+                // pattern is tracked
+                let corners = [
+                    i64::from(a.range.lo) << b_lo,
+                    i64::from(a.range.lo) << b_hi,
+                    i64::from(a.range.hi) << b_lo,
+                    i64::from(a.range.hi) << b_hi,
+                ];
+                let value = Value::from(hull(&corners).unwrap_or_else(Interval::full));
+                if b_lo == b_hi {
+                    value.with_bits(KnownBits {
+                        zeros: (a.bits.zeros << b_lo) | low_mask(b_lo),
+                        ones: a.bits.ones << b_lo,
+                    })
+                } else {
+                    value
+                }
+            }
+        }
+    }
+}
+
+/// The low bits of a sum, difference or product only depend on the low
+/// bits of its operands, so they are known as far as both operands' are.
+fn low_bits(a: KnownBits, b: KnownBits, op: fn(u32, u32) -> u32) -> KnownBits {
+    let known = cmp::min(known_low_bits(a), known_low_bits(b));
+    let mask = low_mask(known);
+    let result = op(a.ones, b.ones) & mask;
+    KnownBits {
+        zeros: !result & mask,
+        ones: result,
+    }
+}
+
+fn known_low_bits(bits: KnownBits) -> u32 {
+    (!(bits.zeros | bits.ones)).trailing_zeros()
+}
+
+fn trailing_zeros(bits: KnownBits) -> u32 {
+    (!bits.zeros).trailing_zeros()
+}
+
+fn low_mask(n: u32) -> u32 {
+    if n >= 32 {
+        !0
+    } else {
+        (1 << n) - 1
+    }
+}
+
+/// The smallest interval containing all of `xs`, if it fits in an `i32`.
+fn hull(xs: &[i64]) -> Option<Interval> {
+    let lo = xs.iter().cloned().min().unwrap();
+    let hi = xs.iter().cloned().max().unwrap();
+    Interval::clamp(lo, hi)
+}
@@ -1,27 +1,51 @@
-use eval::eval;
+use eval::{eval, eval_block, EvalContext};
 use expr::Expr;
+use limits::{self, Limits};
 use sample::signal::Signal;
 
 #[derive(Clone)]
 pub struct ExprSignal {
     pub time: i32,
-    expression: Expr,
+    context: EvalContext,
+    buffer: Vec<i32>,
 }
 
 impl From<Expr> for ExprSignal {
     fn from(expr: Expr) -> ExprSignal {
         ExprSignal {
             time: 0,
-            expression: expr,
+            context: EvalContext::new(expr),
+            buffer: Vec::new(),
         }
     }
 }
 
+impl ExprSignal {
+    /// Builds a signal only if `expr` is within `limits`: its depth, its
+    /// number of nodes and the operations it needs per sample.
+    pub fn with_limits(expr: Expr, limits: &Limits) -> Result<ExprSignal, limits::Error> {
+        limits.check_expr(&expr)?;
+        Ok(ExprSignal::from(expr))
+    }
+
+    /// Renders `frames.len()` frames at once, exactly as that many calls to
+    /// `next` would.
+    pub fn fill(&mut self, frames: &mut [<Self as Signal>::Frame]) {
+        self.buffer.resize(frames.len(), 0);
+        eval_block(&mut self.context, self.time, &mut self.buffer);
+
+        for (frame, &x) in frames.iter_mut().zip(self.buffer.iter()) {
+            *frame = [x as i8];
+        }
+        self.time += frames.len() as i32;
+    }
+}
+
 impl Signal for ExprSignal {
     type Frame = [i8; 1];
 
     fn next(&mut self) -> Self::Frame {
-        if let Ok(x) = eval(self.time, &self.expression) {
+        if let Ok(x) = eval(self.time, self.context.expression()) {
             self.time +=1;
             [x as i8]
         } else {
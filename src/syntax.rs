@@ -0,0 +1,766 @@
+use std::fmt;
+use std::mem;
+
+use nom::IResult;
+
+use expr::Expr;
+use numeral::number;
+use ops::*;
+use parser::{check_depth, incomplete, leaf, Expected, Span};
+use self::Expr::*;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TokenKind {
+    /// Spaces, tabs and line breaks.
+    Whitespace,
+    /// `//` and the rest of the line, without the line break.
+    Comment,
+    /// `t` or `T`.
+    Time,
+    /// A literal. Literals have no sign, so the `-` in `-5` is an operator.
+    Number,
+    Operator(OperatorClass),
+    OpenParen,
+    CloseParen,
+    /// A word other than `t`. The grammar has no names, so it is always an
+    /// error, but it is a word rather than a run of bad characters.
+    Identifier,
+    /// Input that can't be read as any token, such as `$` or a number too
+    /// big for an `i32`.
+    Error,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum OperatorClass {
+    /// `*`, `/`, `+` and binary `-`.
+    Arithmetic,
+    /// `<<` and `>>`.
+    Shift,
+    /// `&`, `^` and `|`.
+    Bitwise,
+    /// `-`, `!` and `~` before an operand.
+    Unary,
+}
+
+impl OperatorClass {
+    pub fn of(op: BinOp) -> Self {
+        match op {
+            BinOp::One(_) | BinOp::Two(_) => OperatorClass::Arithmetic,
+            BinOp::Three(_) => OperatorClass::Shift,
+            BinOp::Four(_) | BinOp::Five(_) | BinOp::Six(_) => OperatorClass::Bitwise,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub text: String,
+    /// Where the token was in the source it was parsed from.
+    pub span: Span,
+}
+
+/// A token with the whitespace and comments before it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SyntaxToken {
+    pub leading: Vec<Token>,
+    pub token: Token,
+}
+
+/// A node of the syntax tree, with the parentheses around it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SyntaxNode {
+    /// The `(` before the node, innermost first.
+    pub open: Vec<SyntaxToken>,
+    pub kind: NodeKind,
+    /// The `)` after the node, innermost first.
+    pub close: Vec<SyntaxToken>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum NodeKind {
+    Time(SyntaxToken),
+    Number(SyntaxToken),
+    Unary(SyntaxToken, Box<SyntaxNode>),
+    Binary(Box<SyntaxNode>, SyntaxToken, Box<SyntaxNode>),
+    /// An operand that is missing, with an empty token, or that can't be
+    /// read, from `parse_recovering`.
+    Error(SyntaxToken),
+}
+
+/// A parsed expression that keeps every byte of its source: printing it
+/// gives back exactly the text it was parsed from.
+///
+/// The fields are public so that tools can rewrite part of the tree and
+/// print it again, leaving the layout of the rest untouched. Spans always
+/// refer to the source the tree was parsed from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SyntaxTree {
+    pub root: SyntaxNode,
+    /// Whitespace and comments after the last token.
+    pub trailing: Vec<Token>,
+}
+
+impl SyntaxTree {
+    /// The expression the tree stands for, as `parser::parse_with_comments`
+    /// would give for its text. Fails only if an edit left a token that doesn't read
+    /// as what its place in the tree needs.
+    pub fn to_expr(&self) -> Result<Expr, String> {
+        self.root.to_expr()
+    }
+
+    /// Every token in the tree in source order, whitespace and comments
+    /// included.
+    pub fn tokens(&self) -> Vec<&Token> {
+        let mut tokens = Vec::new();
+        self.root.collect(&mut tokens);
+        tokens.extend(&self.trailing);
+        tokens
+    }
+}
+
+impl SyntaxNode {
+    pub fn to_expr(&self) -> Result<Expr, String> {
+        match self.kind {
+            NodeKind::Time(ref token) => match &*token.token.text {
+                "t" | "T" => Ok(Time),
+                text => Err(format!("`{}` is not `t`", text)),
+            },
+            NodeKind::Number(ref token) => {
+                let text = &token.token.text;
+                match number(text.as_bytes()) {
+                    IResult::Done(&[], numeral) => Ok(Num(numeral)),
+                    _ => Err(format!("`{}` is not a number", text)),
+                }
+            }
+            NodeKind::Unary(ref token, ref operand) => {
+                let op = match &*token.token.text {
+                    "-" => UnOp::Neg,
+                    "!" => UnOp::BoolNot,
+                    "~" => UnOp::BitNot,
+                    text => return Err(format!("`{}` is not a unary operator", text)),
+                };
+                Ok(UnExpr(op, Box::new(operand.to_expr()?)))
+            }
+            NodeKind::Binary(ref left, ref token, ref right) => {
+                let text = &token.token.text;
+                let op = match binary_op(text.as_bytes()) {
+                    IResult::Done(&[], op) => op,
+                    _ => return Err(format!("`{}` is not a binary operator", text)),
+                };
+                Ok(BinExpr(Box::new(left.to_expr()?), op, Box::new(right.to_expr()?)))
+            }
+            NodeKind::Error(ref token) => Err(format!("`{}` is not an operand", token.token.text)),
+        }
+    }
+
+    /// From the start of the outermost `(` around the node to the end of
+    /// the outermost `)`, leaving out whitespace and comments before it.
+    pub fn span(&self) -> Span {
+        Span {
+            start: self.first().span.start,
+            end: self.last().span.end,
+        }
+    }
+
+    fn first(&self) -> &Token {
+        if let Some(open) = self.open.last() {
+            return &open.token;
+        }
+        match self.kind {
+            NodeKind::Time(ref token) |
+            NodeKind::Number(ref token) |
+            NodeKind::Unary(ref token, _) |
+            NodeKind::Error(ref token) => &token.token,
+            NodeKind::Binary(ref left, _, _) => left.first(),
+        }
+    }
+
+    fn last(&self) -> &Token {
+        if let Some(close) = self.close.last() {
+            return &close.token;
+        }
+        match self.kind {
+            NodeKind::Time(ref token) | NodeKind::Number(ref token) | NodeKind::Error(ref token) => {
+                &token.token
+            }
+            NodeKind::Unary(_, ref operand) | NodeKind::Binary(_, _, ref operand) => operand.last(),
+        }
+    }
+
+    /// Takes the whitespace and comments before the node's first token.
+    fn take_leading(&mut self) -> Vec<Token> {
+        let token = match self.open.last_mut() {
+            Some(open) => open,
+            None => match self.kind {
+                NodeKind::Time(ref mut token) |
+                NodeKind::Number(ref mut token) |
+                NodeKind::Unary(ref mut token, _) |
+                NodeKind::Error(ref mut token) => token,
+                NodeKind::Binary(ref mut left, _, _) => return left.take_leading(),
+            },
+        };
+        mem::take(&mut token.leading)
+    }
+
+    fn collect<'a>(&'a self, tokens: &mut Vec<&'a Token>) {
+        for open in self.open.iter().rev() {
+            open.collect(tokens);
+        }
+        match self.kind {
+            NodeKind::Time(ref token) | NodeKind::Number(ref token) | NodeKind::Error(ref token) => {
+                token.collect(tokens)
+            }
+            NodeKind::Unary(ref token, ref operand) => {
+                token.collect(tokens);
+                operand.collect(tokens);
+            }
+            NodeKind::Binary(ref left, ref token, ref right) => {
+                left.collect(tokens);
+                token.collect(tokens);
+                right.collect(tokens);
+            }
+        }
+        for close in &self.close {
+            close.collect(tokens);
+        }
+    }
+
+    fn write(&self, out: &mut String) {
+        for open in self.open.iter().rev() {
+            open.write(out);
+        }
+        match self.kind {
+            NodeKind::Time(ref token) | NodeKind::Number(ref token) | NodeKind::Error(ref token) => {
+                token.write(out)
+            }
+            NodeKind::Unary(ref token, ref operand) => {
+                token.write(out);
+                operand.write(out);
+            }
+            NodeKind::Binary(ref left, ref token, ref right) => {
+                left.write(out);
+                token.write(out);
+                right.write(out);
+            }
+        }
+        for close in &self.close {
+            close.write(out);
+        }
+    }
+}
+
+impl SyntaxToken {
+    fn collect<'a>(&'a self, tokens: &mut Vec<&'a Token>) {
+        tokens.extend(&self.leading);
+        tokens.push(&self.token);
+    }
+
+    fn write(&self, out: &mut String) {
+        for trivia in &self.leading {
+            out.push_str(&trivia.text);
+        }
+        out.push_str(&self.token.text);
+    }
+}
+
+/// Prints the tree's text, which is its source unless it has been edited.
+impl fmt::Display for SyntaxTree {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut out = String::new();
+        self.root.write(&mut out);
+        for trivia in &self.trailing {
+            out.push_str(&trivia.text);
+        }
+        f.write_str(&out)
+    }
+}
+
+impl fmt::Display for SyntaxNode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut out = String::new();
+        self.write(&mut out);
+        f.write_str(&out)
+    }
+}
+
+/// Parses an expression into a lossless syntax tree. It accepts the same
+/// inputs as `parser::parse_with_comments`, `//` comments included, with
+/// the same errors, and groups them the same way.
+pub fn parse_syntax(input: &str) -> Result<SyntaxTree, String> {
+    let (tree, errors) = parse_recovering(input);
+    match errors.into_iter().next() {
+        Some(error) => Err(error.message),
+        None => Ok(tree),
+    }
+}
+
+/// A syntax error found by `parse_recovering`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SyntaxError {
+    /// The input that is wrong, or an empty span where something is missing.
+    pub span: Span,
+    /// What would have been valid there, if anything.
+    pub expected: Option<Expected>,
+    /// Worded as `parser::parse` words its error, which is the first of
+    /// these.
+    pub message: String,
+}
+
+/// Parses any input into a syntax tree, reporting every syntax error
+/// instead of stopping at the first.
+///
+/// A missing operand, or input that can't start one, becomes an `Error`
+/// node. Input where an operator should be becomes the operator token of a
+/// binary node, with kind `TokenKind::Error` and empty text if nothing is
+/// there, binding looser than any real operator. A `)` with no `(` stays
+/// after the operand before it, and a `(` that is never closed stays open.
+/// Parts nested deeper than `parse` allows are turned into error nodes.
+/// The tree still prints as exactly the input.
+pub fn parse_recovering(input: &str) -> (SyntaxTree, Vec<SyntaxError>) {
+    Builder::new(input).parse()
+}
+
+/// Splits `input` into tokens for highlighting, whitespace and comments
+/// included, so their texts add up to `input`. It never fails: anything
+/// that isn't a token comes back as an `Error` token.
+///
+/// A `-` where an operand is expected is a unary operator, and a binary
+/// one after an operand. Valid expressions come out as the tokens of their
+/// `SyntaxTree`.
+pub fn tokenize(input: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut offset = 0;
+    // whether the last token ended an operand, so an operator should follow
+    let mut after_operand = false;
+    while offset < input.len() {
+        let (kind, len) = next_token(&input[offset..], after_operand);
+        match kind {
+            TokenKind::Time | TokenKind::Number | TokenKind::Identifier | TokenKind::CloseParen => {
+                after_operand = true
+            }
+            TokenKind::Operator(_) | TokenKind::OpenParen => after_operand = false,
+            TokenKind::Whitespace | TokenKind::Comment | TokenKind::Error => {}
+        }
+        tokens.push(Token {
+            kind,
+            text: input[offset..offset + len].to_owned(),
+            span: Span {
+                start: offset,
+                end: offset + len,
+            },
+        });
+        offset += len;
+    }
+    tokens
+}
+
+/// The kind and length of the token at the start of `rest`.
+fn next_token(rest: &str, after_operand: bool) -> (TokenKind, usize) {
+    let bytes = rest.as_bytes();
+    let first = bytes[0];
+
+    if matches!(first, b' ' | b'\t' | b'\r' | b'\n') {
+        let len = bytes
+            .iter()
+            .take_while(|&&c| matches!(c, b' ' | b'\t' | b'\r' | b'\n'))
+            .count();
+        (TokenKind::Whitespace, len)
+    } else if rest.starts_with("//") {
+        (TokenKind::Comment, rest.find('\n').unwrap_or(rest.len()))
+    } else if first.is_ascii_alphabetic() || first == b'_' {
+        let len = bytes
+            .iter()
+            .take_while(|&&c| c.is_ascii_alphanumeric() || c == b'_')
+            .count();
+        match &rest[..len] {
+            "t" | "T" => (TokenKind::Time, 1),
+            _ => (TokenKind::Identifier, len),
+        }
+    } else if first.is_ascii_digit() {
+        // every digit and point, so `1.2.3` is one bad number
+        let len = bytes
+            .iter()
+            .take_while(|&&c| c.is_ascii_digit() || c == b'.')
+            .count();
+        match number(&bytes[..len]) {
+            IResult::Done(&[], _) => (TokenKind::Number, len),
+            _ => (TokenKind::Error, len),
+        }
+    } else if first == b'(' {
+        (TokenKind::OpenParen, 1)
+    } else if first == b')' {
+        (TokenKind::CloseParen, 1)
+    } else if first == b'!' || first == b'~' || (first == b'-' && !after_operand) {
+        (TokenKind::Operator(OperatorClass::Unary), 1)
+    } else if let IResult::Done(after, op) = binary_op(bytes) {
+        (TokenKind::Operator(OperatorClass::of(op)), bytes.len() - after.len())
+    } else {
+        let len = rest.chars().next().map_or(1, char::len_utf8);
+        (TokenKind::Error, len)
+    }
+}
+
+/// An operator waiting for its operands.
+enum Pending {
+    Paren(SyntaxToken),
+    Unary(SyntaxToken),
+    /// A binary operator, or `None` for input that stands where one should
+    /// be.
+    Binary(SyntaxToken, Option<BinOp>),
+}
+
+/// Parses like `parser::Parser`, keeping the tokens, and carries on past
+/// errors.
+struct Builder<'a> {
+    input: &'a str,
+    offset: usize,
+    /// Whitespace and comments since the last token.
+    trivia: Vec<Token>,
+    /// Finished nodes, with their depth.
+    operands: Vec<(SyntaxNode, usize)>,
+    operators: Vec<Pending>,
+    errors: Vec<SyntaxError>,
+    /// Whether the input has been reported as ending too early.
+    incomplete: bool,
+}
+
+impl<'a> Builder<'a> {
+    fn new(input: &'a str) -> Self {
+        Builder {
+            input,
+            offset: 0,
+            trivia: Vec::new(),
+            operands: Vec::new(),
+            operators: Vec::new(),
+            errors: Vec::new(),
+            incomplete: false,
+        }
+    }
+
+    fn parse(mut self) -> (SyntaxTree, Vec<SyntaxError>) {
+        loop {
+            self.parse_operand();
+            if !self.parse_operator() {
+                break;
+            }
+        }
+
+        // parentheses still open at the end stay open
+        let open_parens = self.open_parens();
+        while let Some(pending) = self.operators.pop() {
+            match pending {
+                Pending::Binary(token, _) => self.reduce(token),
+                Pending::Paren(open) => {
+                    self.incomplete(Expected::Operator { open_parens });
+                    let (mut node, depth) = self.operands.pop().expect("parsed an operand");
+                    node.open.push(open);
+                    self.push_operand(node, depth);
+                }
+                Pending::Unary(_) => unreachable!("unary operators are applied eagerly"),
+            }
+        }
+
+        let (root, _) = self.operands.pop().expect("parsed an operand");
+        let tree = SyntaxTree {
+            root,
+            trailing: self.trivia,
+        };
+        (tree, self.errors)
+    }
+
+    /// Reads prefix operators and open parentheses up to and including the
+    /// next operand, putting an error node in place of a missing or invalid
+    /// one.
+    fn parse_operand(&mut self) {
+        loop {
+            self.skip_trivia();
+            let rest = self.rest();
+            if rest.is_empty() {
+                self.incomplete(Expected::Operand);
+                return self.missing_operand();
+            }
+
+            if let IResult::Done(after, expression) = leaf(rest) {
+                let kind = match expression {
+                    Time => TokenKind::Time,
+                    _ => TokenKind::Number,
+                };
+                let token = self.token(kind, rest.len() - after.len());
+                let kind = match kind {
+                    TokenKind::Time => NodeKind::Time(token),
+                    _ => NodeKind::Number(token),
+                };
+                return self.push_operand(new_node(kind), 1);
+            }
+
+            if rest[0] == b'(' {
+                let token = self.token(TokenKind::OpenParen, 1);
+                self.operators.push(Pending::Paren(token));
+                continue;
+            }
+
+            match unop(rest) {
+                IResult::Done(after, _) => {
+                    let kind = TokenKind::Operator(OperatorClass::Unary);
+                    let token = self.token(kind, rest.len() - after.len());
+                    self.operators.push(Pending::Unary(token));
+                }
+                IResult::Incomplete(_) | IResult::Error(_) => {
+                    let message = format!("expected {} at offset {}", Expected::Operand, self.offset);
+                    if !is_junk(rest) {
+                        // an operator or `)`, so the operand is missing
+                        self.error(0, Some(Expected::Operand), message);
+                        return self.missing_operand();
+                    }
+                    let len = self.junk_len();
+                    self.error(len, Some(Expected::Operand), message);
+                    let token = self.token(TokenKind::Error, len);
+                    return self.push_operand(new_node(NodeKind::Error(token)), 1);
+                }
+            }
+        }
+    }
+
+    /// Reads closing parentheses up to and including the next binary
+    /// operator, taking anything else as an operator that is wrong or
+    /// missing. Returns false at the end of the input.
+    fn parse_operator(&mut self) -> bool {
+        loop {
+            self.skip_trivia();
+            let rest = self.rest();
+            if rest.is_empty() {
+                return false;
+            }
+
+            if rest[0] == b')' {
+                self.close_paren();
+                continue;
+            }
+
+            match binary_op(rest) {
+                IResult::Done(after, op) => {
+                    let kind = TokenKind::Operator(OperatorClass::of(op));
+                    let token = self.token(kind, rest.len() - after.len());
+                    self.push_binary(token, Some(op));
+                }
+                // `<` or `>` at the very end
+                IResult::Incomplete(_) => {
+                    let shift = match rest[0] {
+                        b'<' => BitShift::Left,
+                        _ => BitShift::Right,
+                    };
+                    let token = self.token(TokenKind::Error, 1);
+                    self.incomplete(Expected::Shift(shift));
+                    self.push_binary(token, None);
+                }
+                IResult::Error(_) => {
+                    let expected = Expected::Operator { open_parens: self.open_parens() };
+                    let message = format!("unexpected input at offset {}", self.offset);
+                    // an operand right after another has a missing operator between
+                    let len = if is_junk(rest) { self.junk_len() } else { 0 };
+                    self.error(len, Some(expected), message);
+                    let token = self.token(TokenKind::Error, len);
+                    self.push_binary(token, None);
+                }
+            }
+            return true;
+        }
+    }
+
+    fn push_binary(&mut self, token: SyntaxToken, op: Option<BinOp>) {
+        // equal levels stay on the stack, which groups them to the right
+        loop {
+            match self.operators.last() {
+                Some(&Pending::Binary(_, top)) if level(top) < level(op) => {}
+                _ => break,
+            }
+            if let Some(Pending::Binary(top, _)) = self.operators.pop() {
+                self.reduce(top);
+            }
+        }
+        self.operators.push(Pending::Binary(token, op));
+    }
+
+    /// Closes the innermost open parenthesis, or reports the `)` and keeps
+    /// it after the operand before it if none is open.
+    fn close_paren(&mut self) {
+        let open = loop {
+            match self.operators.pop() {
+                Some(Pending::Binary(token, _)) => self.reduce(token),
+                Some(Pending::Paren(open)) => break Some(open),
+                Some(Pending::Unary(_)) => unreachable!("unary operators are applied eagerly"),
+                None => break None,
+            }
+        };
+        if open.is_none() {
+            let message = format!("unmatched `)` at offset {}", self.offset);
+            self.error(1, Some(Expected::Operator { open_parens: 0 }), message);
+        }
+
+        let close = self.token(TokenKind::CloseParen, 1);
+        let (mut node, depth) = self.operands.pop().expect("parsed an operand");
+        node.open.extend(open);
+        node.close.push(close);
+        self.push_operand(node, depth);
+    }
+
+    fn push_operand(&mut self, mut node: SyntaxNode, mut depth: usize) {
+        while let Some(&Pending::Unary(_)) = self.operators.last() {
+            if let Some(Pending::Unary(token)) = self.operators.pop() {
+                node = new_node(NodeKind::Unary(token, Box::new(node)));
+            }
+            depth += 1;
+            if let Err(message) = check_depth(depth) {
+                node = self.too_deep(node, message);
+                depth = 1;
+            }
+        }
+        self.operands.push((node, depth));
+    }
+
+    fn reduce(&mut self, token: SyntaxToken) {
+        let (b, depth_b) = self.operands.pop().expect("parsed an operand");
+        let (a, depth_a) = self.operands.pop().expect("parsed an operand");
+        let mut node = new_node(NodeKind::Binary(Box::new(a), token, Box::new(b)));
+        let mut depth = 1 + depth_a.max(depth_b);
+        if let Err(message) = check_depth(depth) {
+            node = self.too_deep(node, message);
+            depth = 1;
+        }
+        self.operands.push((node, depth));
+    }
+
+    /// Replaces a node nested too deeply with an error node holding its
+    /// text, so the tree never gets deeper than `parse` allows. The
+    /// whitespace and comments before the node stay in front of the error
+    /// token.
+    fn too_deep(&mut self, mut node: SyntaxNode, message: String) -> SyntaxNode {
+        let span = node.span();
+        self.errors.push(SyntaxError {
+            span,
+            expected: None,
+            message,
+        });
+        let leading = node.take_leading();
+        let token = Token {
+            kind: TokenKind::Error,
+            text: node.to_string(),
+            span,
+        };
+        new_node(NodeKind::Error(SyntaxToken { leading, token }))
+    }
+
+    fn missing_operand(&mut self) {
+        let token = self.token(TokenKind::Error, 0);
+        self.push_operand(new_node(NodeKind::Error(token)), 1);
+    }
+
+    /// The length of the run of tokens from here that can't start an
+    /// operand or an operator.
+    fn junk_len(&self) -> usize {
+        let rest = &self.input[self.offset..];
+        let mut len = 0;
+        while len < rest.len() && (len == 0 || is_junk(&rest.as_bytes()[len..])) {
+            len += next_token(&rest[len..], false).1;
+        }
+        len
+    }
+
+    fn error(&mut self, len: usize, expected: Option<Expected>, message: String) {
+        self.errors.push(SyntaxError {
+            span: Span {
+                start: self.offset,
+                end: self.offset + len,
+            },
+            expected,
+            message,
+        });
+    }
+
+    /// Reports the end of the input, once, as coming too early.
+    fn incomplete(&mut self, expected: Expected) {
+        if !self.incomplete {
+            self.incomplete = true;
+            self.error(0, Some(expected), incomplete());
+        }
+    }
+
+    fn open_parens(&self) -> usize {
+        self.operators
+            .iter()
+            .filter(|pending| matches!(**pending, Pending::Paren(_)))
+            .count()
+    }
+
+    /// Collects whitespace and comments, as `parser::Parser` skips them when
+    /// it reads comments.
+    fn skip_trivia(&mut self) {
+        loop {
+            let rest = self.rest();
+            let whitespace = rest
+                .iter()
+                .take_while(|&&c| matches!(c, b' ' | b'\t' | b'\r' | b'\n'))
+                .count();
+            if whitespace > 0 {
+                let token = self.lex(TokenKind::Whitespace, whitespace);
+                self.trivia.push(token);
+            } else if rest.starts_with(b"//") {
+                let comment = rest.iter().position(|&c| c == b'\n').unwrap_or(rest.len());
+                let token = self.lex(TokenKind::Comment, comment);
+                self.trivia.push(token);
+            } else {
+                break;
+            }
+        }
+    }
+
+    /// The next `len` bytes as a token, with the trivia before it.
+    fn token(&mut self, kind: TokenKind, len: usize) -> SyntaxToken {
+        let token = self.lex(kind, len);
+        SyntaxToken {
+            leading: self.trivia.drain(..).collect(),
+            token,
+        }
+    }
+
+    fn lex(&mut self, kind: TokenKind, len: usize) -> Token {
+        let span = Span {
+            start: self.offset,
+            end: self.offset + len,
+        };
+        self.offset = span.end;
+        Token {
+            kind,
+            text: self.input[span.start..span.end].to_owned(),
+            span,
+        }
+    }
+
+    fn rest(&self) -> &'a [u8] {
+        &self.input.as_bytes()[self.offset..]
+    }
+}
+
+/// Whether `rest` starts with something that can't start an operand or an
+/// operator, or be skipped.
+fn is_junk(rest: &[u8]) -> bool {
+    let skipped = matches!(rest[0], b' ' | b'\t' | b'\r' | b'\n' | b'(' | b')') || rest.starts_with(b"//");
+    let operand = matches!(leaf(rest), IResult::Done(..)) || !matches!(unop(rest), IResult::Error(_));
+    let operator = !matches!(binary_op(rest), IResult::Error(_));
+    !(skipped || operand || operator)
+}
+
+/// The precedence level of a pending binary operator. Input in place of an
+/// operator binds loosest, so it splits the expression around it.
+fn level(op: Option<BinOp>) -> u8 {
+    op.map_or(7, |op| op.level())
+}
+
+fn new_node(kind: NodeKind) -> SyntaxNode {
+    SyntaxNode {
+        open: Vec::new(),
+        kind,
+        close: Vec::new(),
+    }
+}
@@ -1,11 +1,45 @@
-use std::error::Error;
+use std::error;
+use std::fmt;
 
-use hound::{WavWriter, WavSpec, SampleFormat};
+use hound::{self, WavWriter, WavSpec, SampleFormat};
+use expr::Expr;
+use limits::{self, Limits};
+use period::{period, Period};
 use signal::ExprSignal;
-use sample::Signal;
+
+const BLOCK_SIZE: usize = 4096;
+
+#[derive(Debug)]
+pub enum Error {
+    Limit(limits::Error),
+    NoPeriod { max_period: usize },
+    Write(hound::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Limit(ref e) => e.fmt(f),
+            Error::NoPeriod { max_period } =>
+                write!(f, "No period of at most {} samples", max_period),
+            Error::Write(ref e) => write!(f, "Failed to write the file: {}", e),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            Error::Limit(ref e) => Some(e),
+            Error::NoPeriod { .. } => None,
+            Error::Write(ref e) => Some(e),
+        }
+    }
+}
 
 pub struct Recorder {
     spec: WavSpec,
+    limits: Option<Limits>,
 }
 
 impl Recorder {
@@ -17,6 +51,15 @@ impl Recorder {
                 bits_per_sample: 8,
                 sample_format: SampleFormat::Int,
             },
+            limits: None,
+        }
+    }
+
+    /// A recorder that refuses to render longer than `limits.max_duration`.
+    pub fn with_limits(sample_rate: u32, limits: Limits) -> Self {
+        Recorder {
+            limits: Some(limits),
+            ..Recorder::new(sample_rate)
         }
     }
 
@@ -25,17 +68,53 @@ impl Recorder {
         filename: &'a str,
         duration: f32,
         signal: &'a mut ExprSignal,
-    ) -> Result<(), String> {
+    ) -> Result<(), Error> {
+        if let Some(ref limits) = self.limits {
+            limits.check_duration(duration).map_err(Error::Limit)?;
+        }
+
+        let samples = (self.spec.sample_rate as f32 * duration) as usize;
+        self.write(filename, samples, signal)
+    }
+
+    /// Records exactly one period of `expression`, starting at `t = 0`, so
+    /// that the file can be looped without a seam. Fails if the output
+    /// doesn't repeat within `max_period` samples.
+    pub fn record_loop(
+        &self,
+        filename: &str,
+        expression: &Expr,
+        max_period: usize,
+    ) -> Result<Period, Error> {
+        let found = period(expression, max_period).ok_or(Error::NoPeriod { max_period })?;
+        let samples = match found {
+            Period::Exact(p) | Period::Observed(p) => p,
+        };
+
+        if let Some(ref limits) = self.limits {
+            let duration = samples as f32 / self.spec.sample_rate as f32;
+            limits.check_duration(duration).map_err(Error::Limit)?;
+        }
+
+        self.write(filename, samples, &mut ExprSignal::from(expression.clone()))?;
+        Ok(found)
+    }
+
+    fn write(&self, filename: &str, samples: usize, signal: &mut ExprSignal) -> Result<(), Error> {
         WavWriter::create(filename, self.spec)
             .and_then(|mut writer| {
-                for _ in 0..(self.spec.sample_rate as f32 * duration) as u32 {
-                    match writer.write_sample(signal.next()[0]) {
-                        Ok(_) => continue,
-                        err => return err
+                let mut frames = vec![[0; 1]; BLOCK_SIZE];
+                let mut remaining = samples;
+                while remaining > 0 {
+                    let block = &mut frames[..remaining.min(BLOCK_SIZE)];
+                    signal.fill(block);
+                    for frame in block.iter() {
+                        writer.write_sample(frame[0])?;
                     }
+                    remaining -= block.len();
                 }
                 writer.finalize()
             })
-            .map_err(|e| e.description().to_owned())
+            .map_err(Error::Write)
     }
 }
@@ -0,0 +1,81 @@
+extern crate bbb_core;
+
+use bbb_core::bits::*;
+use bbb_core::parser::parse;
+
+fn symbolic(e: &str) -> Influence {
+    influence(&parse(e).unwrap())
+}
+
+#[test]
+fn symbolic_influence_test() {
+    let time = symbolic("t");
+    for i in 0..32 {
+        assert_eq!(time.bits[i], 1 << i);
+    }
+
+    assert_eq!(symbolic("42").any(), 0);
+    assert_eq!(symbolic("t & 240").any(), 0xf0);
+    assert_eq!(symbolic("t | -16").any(), 0xf);
+    assert_eq!(symbolic("t >> 8 & 3").any(), 0x300);
+    assert_eq!(symbolic("(t & 7) << 4").bits[5], 0x2);
+
+    let sum = symbolic("(t >> 4 & 1) + (t >> 8 & 1)");
+    assert_eq!(sum.bits[0], 0x110);
+    assert_eq!(sum.bits[1], 0x110);
+    assert_eq!(sum.bits[2], 0x110);
+
+    let quotient = symbolic("(t & 3) / (t >> 4 & 1)");
+    assert_eq!(quotient.bits[31], 0x13);
+    assert_eq!(quotient.failure, 0x10);
+}
+
+#[test]
+fn sample_influence_test() {
+    // only the low 8 bits of the output reach a sample
+    assert_eq!(symbolic("t << 8").sample(), 0);
+    assert_eq!(symbolic("t >> 8").sample(), 0xff00);
+}
+
+#[test]
+fn time_scale_test() {
+    assert_eq!(symbolic("t").time_scale(), Some(TimeScale { fastest: 0, slowest: 31 }));
+    assert_eq!(symbolic("1").time_scale(), None);
+
+    let scale = symbolic("t >> 12 & 7").time_scale().unwrap();
+    assert_eq!(scale, TimeScale { fastest: 12, slowest: 14 });
+    assert_eq!(scale.step(), 4096);
+    assert_eq!(scale.cycle(), 32768);
+}
+
+#[test]
+fn sampled_influence_test() {
+    let sampled = sampled_influence(&parse("t / 256 & 3").unwrap(), 16, 64);
+    assert_eq!(sampled.any(), 0x300);
+    assert_eq!(sampled.failure, 0);
+
+    let sampled = sampled_influence(&parse("1 / (t >> 12 & 1)").unwrap(), 16, 64);
+    assert_eq!(sampled.failure, 0x1000);
+
+    // bits of t beyond the sampled ones are never seen
+    assert_eq!(sampled_influence(&parse("t >> 20").unwrap(), 16, 64).any(), 0);
+}
+
+#[test]
+fn influence_map_test() {
+    let parts = influence_map(&parse("(t >> 4 & 1) * (t / 64 & 3)").unwrap(), 16);
+    assert_eq!(parts.len(), 11);
+    assert_eq!(parts[0].path, vec![]);
+    assert_eq!(parts[0].method, Method::Sampled);
+
+    let left = parts.iter().find(|part| part.path == vec![0]).unwrap();
+    assert_eq!(left.method, Method::Symbolic);
+    assert_eq!(left.influence.any(), 0x10);
+
+    let right = parts.iter().find(|part| part.path == vec![1]).unwrap();
+    assert_eq!(right.method, Method::Sampled);
+    assert_eq!(right.influence.any(), 0xc0);
+
+    let time = parts.iter().find(|part| part.path == vec![1, 0, 0]).unwrap();
+    assert_eq!(time.method, Method::Symbolic);
+}
@@ -0,0 +1,59 @@
+// the point is to check that Rust ranks the operators as the parser does
+#![allow(clippy::precedence)]
+
+extern crate bbb_core;
+
+use bbb_core::build::*;
+use bbb_core::expr::Expr;
+use bbb_core::parser::parse;
+
+fn same(built: Builder, text: &str) {
+    assert_eq!(Expr::from(built), parse(text).unwrap(), "{}", text);
+}
+
+#[test]
+fn builds_parsed_tree_test() {
+    same(t() * 5 & t() >> 7, "t*5&t>>7");
+    same(t() * (t() >> 12 | t() >> 8) & 63 & t() >> 4, "(t*(t>>12|t>>8)&63)&t>>4");
+    same(t() | t() >> 8 ^ t() & 255, "t|t>>8^t&255");
+    same(lit(64) - (t() >> 3) + 1, "(64-(t>>3))+1");
+    same(t() + 1 << 2, "t+1<<2");
+    same(-t() / 3, "-t/3");
+    same(!(t() >> 4), "~(t>>4)");
+    same(-lit(5), "-(5)");
+}
+
+#[test]
+fn literal_on_the_left_test() {
+    same(5 * t(), "5*t");
+    same(1 << (t() >> 10 & 7), "1<<(t>>10&7)");
+    same(255 & t(), "255&t");
+}
+
+#[test]
+fn chains_group_left_test() {
+    // the parser groups to the right, so the texts need parentheses
+    same(t() - 1 - 2, "(t-1)-2");
+    same(t() >> 2 >> 1, "(t>>2)>>1");
+    assert!(Expr::from(t() - 1 - 2) != parse("t-1-2").unwrap());
+    same(t() - (lit(1) - 2), "t-1-2");
+}
+
+#[test]
+fn conversions_test() {
+    assert_eq!(lit(5).build(), parse("5").unwrap());
+    assert_eq!(Builder::from(7), lit(7));
+    same(Builder::from(parse("t*3").unwrap()) & 1, "t*3&1");
+}
+
+#[test]
+fn negative_literals_test() {
+    same(lit(-5), "-5");
+    same(t() * -3, "t*-3");
+    same(-1 & t(), "-1&t");
+    same(lit(i32::MIN + 1), "-2147483647");
+    same(lit(i32::MIN), "~2147483647");
+    // printing a built tree reads back as the same tree
+    let built = (t() + -5).build();
+    assert_eq!(parse(&built.to_string()).unwrap(), built);
+}
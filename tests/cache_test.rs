@@ -0,0 +1,76 @@
+extern crate bbb_core;
+
+use bbb_core::cache::*;
+use bbb_core::eval::{eval, eval_block, EvalContext};
+use bbb_core::parser::parse;
+
+#[test]
+fn rate_of_subexpressions() {
+    assert_eq!(rate(&parse("t").unwrap()), Rate::Shift(0));
+    assert_eq!(rate(&parse("(5 * 3) ^ 1").unwrap()), Rate::Constant);
+    assert_eq!(rate(&parse("t >> 12 & 7").unwrap()), Rate::Shift(12));
+    assert_eq!(rate(&parse("(t >> 4) * (t >> 9)").unwrap()), Rate::Shift(4));
+    assert_eq!(rate(&parse("t & -256").unwrap()), Rate::Shift(8));
+    assert_eq!(rate(&parse("t & 0").unwrap()), Rate::Constant);
+    assert_eq!(rate(&parse("t * (t >> 12 & 7)").unwrap()), Rate::Shift(0));
+    assert_eq!(rate(&parse("(t * 3) >> 12").unwrap()), Rate::Shift(0));
+}
+
+#[test]
+fn caches_maximal_subexpressions() {
+    assert_eq!(CachedExpr::new(&parse("t * (t >> 12 & 7)").unwrap()).cached_count(), 1);
+    assert_eq!(CachedExpr::new(&parse("t >> 4 | t >> 14").unwrap()).cached_count(), 1);
+    assert_eq!(CachedExpr::new(&parse("t * 9 & t >> 4 | t * 5 & t >> 7").unwrap()).cached_count(), 2);
+    assert_eq!(CachedExpr::new(&parse("t * 5").unwrap()).cached_count(), 0);
+}
+
+#[test]
+fn output_matches_eval() {
+    let expressions = [
+        ("t * (t >> 12 & 7)", -(1 << 16)),
+        ("(t * 9 & t >> 4 | t * 5 & t >> 7 | t * 3 & t / 1024) - 1", -(1 << 16)),
+        ("((t<<1)^((t<<1)+(t>>7)&t>>12))|t>>(4-(1^7&(t>>19)))|t>>7", 0),
+        ("t / ((t >> 10) & 3) + (t & -512)", -(1 << 16)),
+        ("(1 / 0) + t", 0),
+    ];
+
+    for &(e, t_start) in expressions.iter() {
+        let expression = parse(e).unwrap();
+        let mut cached = CachedExpr::new(&expression);
+        for t in t_start..(1 << 17) {
+            assert_eq!(cached.eval(t), eval(t, &expression), "{} at t = {}", e, t);
+        }
+    }
+}
+
+#[test]
+fn block_rendering_caches_and_matches_eval() {
+    let expressions = [
+        ("t * (t >> 12 & 7)", -(1 << 16)),
+        ("(t * 9 & t >> 4 | t * 5 & t >> 7 | t * 3 & t / 1024) - 1", -(1 << 16)),
+        ("t / ((t >> 10) & 3) + (t & -512)", -(1 << 16)),
+        ("(1 / (t >> 11)) * t", 0),
+        ("t >> 3 ^ 5", i32::MAX - (1 << 15)),
+    ];
+
+    for &(e, t_start) in expressions.iter() {
+        let expression = parse(e).unwrap();
+        let mut ctx = EvalContext::new(expression.clone());
+        assert!(ctx.is_cached(), "{}", e);
+
+        // blocks that don't line up with the runs of cached values
+        let mut out = vec![0; 1000];
+        let mut t = t_start;
+        for _ in 0..100 {
+            eval_block(&mut ctx, t, &mut out);
+            for (i, &x) in out.iter().enumerate() {
+                let time = t.wrapping_add(i as i32);
+                assert_eq!(x, eval(time, &expression).unwrap_or(0), "{} at t = {}", e, time);
+                assert_eq!(ctx.failed()[i], eval(time, &expression).is_err(), "{} at t = {}", e, time);
+            }
+            t = t.wrapping_add(out.len() as i32);
+        }
+    }
+
+    assert!(!EvalContext::new(parse("t * 5").unwrap()).is_cached());
+}
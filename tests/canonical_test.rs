@@ -0,0 +1,81 @@
+extern crate bbb_core;
+
+use std::collections::HashSet;
+
+use bbb_core::canonical::*;
+use bbb_core::eval::eval;
+use bbb_core::expr::Expr::*;
+use bbb_core::numeral::Numeral::*;
+use bbb_core::ops::*;
+use bbb_core::parser::parse;
+
+fn text(e: &str) -> String {
+    canonical_text(&parse(e).unwrap())
+}
+
+#[test]
+fn canonical_text_test() {
+    assert_eq!(text("t"), "t");
+    assert_eq!(text("( t )"), "t");
+    assert_eq!(text("5.7"), "5");
+    assert_eq!(text("t*5&t>>7"), "(t*5)&(t>>7)");
+    assert_eq!(text("(t >> 7) & (5 * t)"), "(t*5)&(t>>7)");
+    assert_eq!(text("t - 1 - 2"), "t-(1-2)");
+    assert_eq!(text("-t + ~(t | 1)"), "-t+~(t|1)");
+    assert_eq!(text("t * -1"), "t*-1");
+}
+
+#[test]
+fn commutative_chains_test() {
+    assert_eq!(text("3 + t + 1"), text("t + 1 + 3"));
+    assert_eq!(text("(3 + t) + 1"), text("3 + (t + 1)"));
+    assert_eq!(text("t ^ 1 ^ t >> 2"), text("(t >> 2 ^ t) ^ 1"));
+    assert!(text("3 - t") != text("t - 3"));
+    // different operators don't mix
+    assert!(text("t + 1 * 2") != text("t * 1 + 2"));
+}
+
+#[test]
+fn canonical_text_parses_back_test() {
+    for e in &["t*5&t>>7", "-(t+1)", "~(-1)^t", "!t*2/(t<<3|t>>5)", "t - -1", "1.5*t"] {
+        let canonical = Canonical::from(&parse(e).unwrap());
+        let parsed = parse(canonical.text()).unwrap();
+        assert_eq!(&parsed, canonical.expression(), "{}", e);
+        assert_eq!(Canonical::from(&parsed), canonical);
+        for t in 0..1000 {
+            assert_eq!(eval(t, &parse(e).unwrap()), eval(t, &parsed), "{} at {}", e, t);
+        }
+    }
+}
+
+#[test]
+fn negative_literals_test() {
+    // a folded constant is the same as the negation the parser reads
+    let folded = BinExpr(Box::new(Time), BinOp::One(BinOp1::Mul), Box::new(Num(Int(-1))));
+    assert_eq!(Canonical::from(&folded), Canonical::from(&parse("t*-1").unwrap()));
+
+    let min = Canonical::from(&Num(Int(i32::MIN)));
+    assert_eq!(min.text(), "~(2147483647)");
+    assert_eq!(&parse(min.text()).unwrap(), min.expression());
+}
+
+#[test]
+fn content_hash_test() {
+    let a = content_hash(&parse("t*5&t>>7").unwrap());
+    let b = content_hash(&parse("(t >> 7) & (t * 5)").unwrap());
+    let c = content_hash(&parse("t*5&t>>8").unwrap());
+    assert_eq!(a, b);
+    assert!(a != c);
+    // fixed for good, so it can be stored
+    assert_eq!(content_hash(&parse("t").unwrap()), 0xaf63_e94c_8602_02a3);
+}
+
+#[test]
+fn dedupe_test() {
+    let submissions = ["t*(t>>8)", "(t >> 8) * t", "t * (t >> 8.0)", "t*(t>>9)"];
+    let unique: HashSet<Canonical> = submissions
+        .iter()
+        .map(|e| Canonical::from(&parse(e).unwrap()))
+        .collect();
+    assert_eq!(unique.len(), 2);
+}
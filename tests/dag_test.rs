@@ -0,0 +1,38 @@
+extern crate bbb_core;
+
+use bbb_core::dag::*;
+use bbb_core::eval::eval;
+use bbb_core::parser::parse;
+
+#[test]
+fn round_trips_through_expr() {
+    let expression = parse("((t<<1)^((t<<1)+(t>>7)&t>>12))|t>>(4-(1^7&(t>>19)))|t>>7").unwrap();
+    assert_eq!(ExprDag::from(&expression).to_expr(), expression);
+}
+
+#[test]
+fn shares_equal_subexpressions() {
+    let dag = ExprDag::from(&parse("(t >> 7) * (t >> 7) + (t >> 7)").unwrap());
+    // t, 7, t >> 7, the product and the sum
+    assert_eq!(dag.stats(), DagStats { tree_nodes: 11, dag_nodes: 5 });
+
+    let dag = ExprDag::from(&parse("1.5 + 1.5 + 1").unwrap());
+    assert_eq!(dag.stats(), DagStats { tree_nodes: 5, dag_nodes: 4 });
+}
+
+#[test]
+fn evaluates_like_eval() {
+    let expressions = [
+        "((t<<1)^((t<<1)+(t>>7)&t>>12))|t>>(4-(1^7&(t>>19)))|t>>7",
+        "(t * 9 & t >> 4 | t * 5 & t >> 7 | t * 3 & t / 1024) - 1",
+        "t / (t >> 4 & 3) + t / (t >> 4 & 3)",
+    ];
+
+    for e in expressions.iter() {
+        let expression = parse(e).unwrap();
+        let mut dag = DagEval::from(ExprDag::from(&expression));
+        for t in 0..1 << 16 {
+            assert_eq!(dag.eval(t), eval(t, &expression), "{} at t = {}", e, t);
+        }
+    }
+}
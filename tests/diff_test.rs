@@ -0,0 +1,116 @@
+extern crate bbb_core;
+
+use bbb_core::diff::*;
+use bbb_core::numeral::Numeral::Int;
+use bbb_core::parser::parse;
+use bbb_core::expr::Expr::*;
+
+fn diffed(old: &str, new: &str) -> Vec<Edit> {
+    diff(&parse(old).unwrap(), &parse(new).unwrap())
+}
+
+#[test]
+fn no_change_test() {
+    assert_eq!(diffed("t*5&t>>7", "(t * 5) & (t >> 7)"), vec![]);
+}
+
+#[test]
+fn replace_test() {
+    let edits = diffed("t*5&t>>7", "t*3&t>>5");
+    assert_eq!(
+        edits,
+        vec![
+            Edit::Replace {
+                old_path: vec![0, 1],
+                new_path: vec![0, 1],
+                old: Num(Int(5)),
+                new: Num(Int(3)),
+            },
+            Edit::Replace {
+                old_path: vec![1, 1],
+                new_path: vec![1, 1],
+                old: Num(Int(7)),
+                new: Num(Int(5)),
+            },
+        ]
+    );
+
+    let old = parse("t*5&t>>7").unwrap();
+    assert_eq!(edits[1].describe(&old), "changed constant 7\u{2192}5 in `t>>7`");
+
+    // floats are quoted as written, not truncated
+    let old = parse("t*1.5").unwrap();
+    let edits = diff(&old, &parse("t*1.9").unwrap());
+    assert_eq!(edits[0].describe(&old), "changed constant 1.5\u{2192}1.9 in `t*1.5`");
+    assert_eq!(
+        render(&old, &parse("t*1.9").unwrap(), &edits),
+        "- t*\x1b[31m1.5\x1b[0m\n+ t*\x1b[32m1.9\x1b[0m\n"
+    );
+}
+
+#[test]
+fn operator_change_test() {
+    let edits = diffed("t>>7", "t<<7");
+    assert_eq!(edits.len(), 1);
+    assert_eq!(
+        edits[0].describe(&parse("t>>7").unwrap()),
+        "changed operator >>\u{2192}<< in `t>>7`"
+    );
+}
+
+#[test]
+fn insert_and_delete_test() {
+    let edits = diffed("t*5|t", "(t*5&255)|t");
+    assert_eq!(
+        edits,
+        vec![Edit::Insert {
+            path: vec![0],
+            new: parse("t*5&255").unwrap(),
+            kept: 0,
+        }]
+    );
+    assert_eq!(edits[0].describe(&parse("t*5|t").unwrap()), "wrapped in `t*5&255`");
+
+    let edits = diffed("~(t>>3)", "t>>3");
+    assert_eq!(
+        edits,
+        vec![Edit::Delete {
+            path: vec![],
+            old: parse("~(t>>3)").unwrap(),
+            kept: 0,
+        }]
+    );
+    assert_eq!(edits[0].describe(&parse("~(t>>3)").unwrap()), "unwrapped `t>>3` from `~(t>>3)`");
+}
+
+#[test]
+fn render_test() {
+    let old = parse("t>>7").unwrap();
+    let new = parse("t>>5").unwrap();
+    assert_eq!(
+        render(&old, &new, &diff(&old, &new)),
+        "- t>>\x1b[31m7\x1b[0m\n+ t>>\x1b[32m5\x1b[0m\n"
+    );
+
+    let old = parse("t").unwrap();
+    let new = parse("-t").unwrap();
+    assert_eq!(
+        render(&old, &new, &diff(&old, &new)),
+        "- t\n+ \x1b[32m-\x1b[0mt\n"
+    );
+
+    // parenthesised only where the printer would
+    let old = parse("t*5|t").unwrap();
+    let new = parse("(t*5&255)|t").unwrap();
+    assert_eq!(
+        render(&old, &new, &diff(&old, &new)),
+        "- t*5|t\n+ t*5\x1b[32m&255\x1b[0m|t\n"
+    );
+
+    let old = parse("t>>3").unwrap();
+    let new = parse("~(t>>3)").unwrap();
+    assert_eq!(
+        render(&old, &new, &diff(&old, &new)),
+        "- t>>3\n+ \x1b[32m~(\x1b[0mt>>3\x1b[32m)\x1b[0m\n"
+    );
+}
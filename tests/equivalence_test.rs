@@ -0,0 +1,91 @@
+extern crate bbb_core;
+
+use bbb_core::equivalence::*;
+use bbb_core::parser::parse;
+use bbb_core::range::Interval;
+
+fn checked(a: &str, b: &str) -> Verdict {
+    Checker::default().check(&parse(a).unwrap(), &parse(b).unwrap())
+}
+
+#[test]
+fn syntactic_test() {
+    assert_eq!(checked("t*5&t>>7", "(t >> 7) & (5 * t)"), Verdict::Identical);
+    assert_eq!(checked("t + 0 | 2 * 3", "6 | t"), Verdict::Identical);
+    assert_eq!(checked("~~t", "t"), Verdict::Identical);
+    assert!(!syntactically_equal(&parse("t - 1").unwrap(), &parse("1 - t").unwrap()));
+    assert!(!syntactically_equal(&parse("t << 1").unwrap(), &parse("t * 2").unwrap()));
+}
+
+#[test]
+fn equivalent_output_test() {
+    let checker = Checker::default();
+    let n = checker.exhaustive.hi as usize + 1 + checker.samples;
+    assert_eq!(checked("t << 1", "t * 2"), Verdict::EquivalentUpTo(n));
+    assert_eq!(checked("t & 255", "t - (t >> 8) * 256"), Verdict::EquivalentUpTo(n));
+    // both fail at every t
+    assert_eq!(checked("t / 0", "1 / 0"), Verdict::EquivalentUpTo(n));
+    // a failure plays as 0
+    assert_eq!(checked("t / 0", "t & 0"), Verdict::EquivalentUpTo(n));
+    // only the low 8 bits are played
+    assert_eq!(checked("t * 256", "t & 0"), Verdict::EquivalentUpTo(n));
+    assert_eq!(checked("t | 768", "t"), Verdict::EquivalentUpTo(n));
+    // arithmetic wraps as it does when playing, whatever the build
+    assert_eq!(checked("t * 65536 * 65536", "t & 0"), Verdict::EquivalentUpTo(n));
+}
+
+#[test]
+fn counterexample_test() {
+    assert_eq!(
+        checked("t - 1", "1 - t"),
+        Verdict::Counterexample {
+            time: 0,
+            left: Ok(-1),
+            right: Ok(1),
+        }
+    );
+    assert_eq!(
+        checked("t & 255", "t / (t >> 4)"),
+        Verdict::Counterexample {
+            time: 1,
+            left: Ok(1),
+            right: Err("division by 0"),
+        }
+    );
+}
+
+#[test]
+fn played_bits_test() {
+    // the low 8 bits differ from `t = 128`, where the value is still
+    // positive but plays as -128
+    assert_eq!(
+        checked("t", "t & 127"),
+        Verdict::Counterexample {
+            time: 128,
+            left: Ok(128),
+            right: Ok(0),
+        }
+    );
+}
+
+#[test]
+fn sampled_finds_late_difference_test() {
+    let a = parse("t >> 20").unwrap();
+    let b = parse("0").unwrap();
+    let small = Interval::new(0, 1000);
+    assert_eq!(check_exhaustive(&a, &b, small), Verdict::EquivalentUpTo(1001));
+
+    match check_sampled(&a, &b, Interval::new(0, (1 << 24) - 1), 100) {
+        Verdict::Counterexample { time, .. } => assert!(time >= 1 << 20),
+        verdict => panic!("expected a counterexample, got {:?}", verdict),
+    }
+
+    let checker = Checker {
+        exhaustive: small,
+        ..Checker::default()
+    };
+    match checker.check(&a, &b) {
+        Verdict::Counterexample { .. } => {}
+        verdict => panic!("expected a counterexample, got {:?}", verdict),
+    }
+}
@@ -0,0 +1,57 @@
+extern crate bbb_core;
+
+use bbb_core::eval::*;
+use bbb_core::parser::parse;
+
+fn assert_block_matches_eval(e: &str, t_start: i32, len: usize) {
+    let expression = parse(e).unwrap();
+    let mut ctx = EvalContext::new(expression.clone());
+    let mut out = vec![0; len];
+    eval_block(&mut ctx, t_start, &mut out);
+
+    for (i, (&x, &failed)) in out.iter().zip(ctx.failed().iter()).enumerate() {
+        let t = t_start + i as i32;
+        match eval(t, &expression) {
+            Ok(y) => assert_eq!((x, failed), (y, false), "{} at t = {}", e, t),
+            Err(_) => assert_eq!((x, failed), (0, true), "{} at t = {}", e, t),
+        }
+    }
+}
+
+#[test]
+fn block_matches_eval() {
+    assert_block_matches_eval("(t * 9 & t >> 4 | t * 5 & t >> 7 | t * 3 & t / 1024) - 1", 0, 1 << 16);
+    assert_block_matches_eval("((t<<1)^((t<<1)+(t>>7)&t>>12))|t>>(4-(1^7&(t>>19)))|t>>7", 0, 1 << 16);
+    assert_block_matches_eval("-t ^ ~(t >> 3) | !t * 2.5", -1024, 2048);
+}
+
+#[test]
+fn block_division_by_zero() {
+    assert_block_matches_eval("t / (t >> 4 & 3) + 7", 0, 4096);
+    assert_block_matches_eval("(1 / 0) * t", 0, 64);
+}
+
+#[test]
+fn context_is_reusable() {
+    let mut ctx = EvalContext::new(parse("t / (t & 1)").unwrap());
+    let mut out = [0; 4];
+
+    eval_block(&mut ctx, 0, &mut out);
+    assert_eq!(out, [0, 1, 0, 3]);
+    assert_eq!(ctx.failed(), &[true, false, true, false]);
+
+    eval_block(&mut ctx, 5, &mut out[..2]);
+    assert_eq!(&out[..2], &[5, 0]);
+    assert_eq!(ctx.failed(), &[false, true]);
+}
+
+#[test]
+fn deep_expression() {
+    let mut e = bbb_core::expr::Expr::Time;
+    for i in 0..5000 {
+        let op = if i % 2 == 0 { bbb_core::ops::UnOp::Neg } else { bbb_core::ops::UnOp::BitNot };
+        e = bbb_core::expr::Expr::UnExpr(op, Box::new(e));
+    }
+    // each `-` followed by `~` adds one
+    assert_eq!(eval(10, &e), Ok(10 - 2500));
+}
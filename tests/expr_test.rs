@@ -0,0 +1,133 @@
+extern crate bbb_core;
+
+use bbb_core::expr::*;
+use bbb_core::numeral::Numeral;
+use bbb_core::numeral::Numeral::*;
+use bbb_core::ops::*;
+use bbb_core::parser::parse;
+
+#[test]
+fn helpers_test() {
+    let expression = parse("t*5&-(t>>7)").unwrap();
+    assert_eq!(expression.node_count(), 8);
+    assert_eq!(expression.op_count(), 4);
+    assert_eq!(expression.depth(), 4);
+    assert_eq!(Expr::Time.depth(), 1);
+
+    let children: Vec<&Expr> = expression.children().collect();
+    assert_eq!(children, vec![&parse("t*5").unwrap(), &parse("-(t>>7)").unwrap()]);
+    assert_eq!(Expr::Time.children().count(), 0);
+}
+
+#[test]
+fn subexpressions_test() {
+    let expression = parse("t*5&t>>7").unwrap();
+    let nums: Vec<Numeral> = expression
+        .subexpressions()
+        .filter_map(|expr| match *expr {
+            Expr::Num(n) => Some(n),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(nums, vec![Int(5), Int(7)]);
+    assert_eq!(expression.subexpressions().next(), Some(&expression));
+}
+
+#[test]
+fn deep_helpers_test() {
+    let mut expression = Expr::Time;
+    for _ in 0..100_000 {
+        expression = Expr::UnExpr(UnOp::BitNot, Box::new(expression));
+    }
+    assert_eq!(expression.depth(), 100_001);
+    assert_eq!(expression.node_count(), 100_001);
+    // dropping it recursively would overflow the stack
+    while let Expr::UnExpr(_, inner) = expression {
+        expression = *inner;
+    }
+}
+
+struct Shifts(Vec<BinOp>);
+
+impl Visitor for Shifts {
+    fn visit_binexpr(&mut self, expr1: &Expr, op: BinOp, expr2: &Expr) {
+        if let BinOp::Three(_) = op {
+            self.0.push(op);
+        }
+        self.visit(expr1);
+        self.visit(expr2);
+    }
+}
+
+#[test]
+fn visitor_test() {
+    let mut shifts = Shifts(Vec::new());
+    shifts.visit(&parse("t>>7|(t<<2)*(t>>1)").unwrap());
+    assert_eq!(
+        shifts.0,
+        vec![
+            BinOp::Three(BitShift::Right),
+            BinOp::Three(BitShift::Left),
+            BinOp::Three(BitShift::Right),
+        ]
+    );
+}
+
+struct Leaves(usize);
+
+impl Visitor for Leaves {
+    fn visit_time(&mut self) {
+        self.0 += 1;
+    }
+
+    fn visit_num(&mut self, _num: Numeral) {
+        self.0 += 1;
+    }
+}
+
+#[test]
+fn visitor_defaults_test() {
+    let mut leaves = Leaves(0);
+    leaves.visit(&parse("~t*5&t>>7").unwrap());
+    assert_eq!(leaves.0, 4);
+}
+
+struct Bump;
+
+impl Fold for Bump {
+    fn fold_num(&mut self, num: Numeral) -> Expr {
+        match num {
+            Int(i) => Expr::Num(Int(i + 1)),
+            Float(f) => Expr::Num(Float(f + 1.0)),
+        }
+    }
+}
+
+struct SwapShifts;
+
+impl Fold for SwapShifts {
+    fn fold(&mut self, expression: Expr) -> Expr {
+        match walk_fold(self, expression) {
+            Expr::BinExpr(a, BinOp::Three(BitShift::Left), b) => {
+                Expr::BinExpr(a, BinOp::Three(BitShift::Right), b)
+            }
+            expression => expression,
+        }
+    }
+}
+
+#[test]
+fn fold_test() {
+    assert_eq!(Bump.fold(parse("t*5&t>>7").unwrap()), parse("t*6&t>>8").unwrap());
+    assert_eq!(SwapShifts.fold(parse("-(t<<2)|1<<t").unwrap()), parse("-(t>>2)|1>>t").unwrap());
+}
+
+#[test]
+fn literal_test() {
+    assert_eq!(parse("5").unwrap().literal(), Some(5));
+    assert_eq!(parse("2.9").unwrap().literal(), Some(2));
+    assert_eq!(parse("--7").unwrap().literal(), Some(7));
+    assert_eq!(parse("-2147483647").unwrap().literal(), Some(-2147483647));
+    assert_eq!(parse("~1").unwrap().literal(), None);
+    assert_eq!(parse("-t").unwrap().literal(), None);
+}
@@ -0,0 +1,116 @@
+extern crate bbb_core;
+
+use bbb_core::formatter::*;
+use bbb_core::parser::{parse, parse_with_comments, Comment};
+
+const PLAYER: &str = "((t<<1)^((t<<1)+(t>>7)&t>>12))|t>>(4-(1^7&(t>>19)))|t>>7";
+
+fn formatted(e: &str, width: usize) -> String {
+    format(&parse(e).unwrap(), &Config { width, keep_comments: true })
+}
+
+#[test]
+fn fits_on_one_line_test() {
+    assert_eq!(formatted("t*5&t>>7", 80), "t * 5 & t >> 7\n");
+    assert_eq!(formatted("t*5&t>>7", 14), "t * 5 & t >> 7\n");
+}
+
+#[test]
+fn breaks_loosest_level_test() {
+    assert_eq!(
+        formatted(PLAYER, 40),
+        concat!(
+            "  t << 1 ^ (t << 1) + (t >> 7) & t >> 12\n",
+            "| t >> 4 - (1 ^ 7 & t >> 19)\n",
+            "| t >> 7\n",
+        )
+    );
+}
+
+#[test]
+fn aligns_operators_test() {
+    assert_eq!(
+        formatted("t*9&t>>4|t*5&t>>7|t*3&t/1024", 20),
+        concat!(
+            "  t * 9 & t >> 4\n",
+            "| t * 5 & t >> 7\n",
+            "| t * 3 & t / 1024\n",
+        )
+    );
+    assert_eq!(
+        formatted("t>>4<<t>>5", 8),
+        concat!(
+            "   t\n",
+            ">> 4\n",
+            "<< t\n",
+            ">> 5\n",
+        )
+    );
+}
+
+#[test]
+fn breaks_nested_operands_test() {
+    assert_eq!(
+        formatted("~((t>>4)*(t&255))|t*2", 18),
+        concat!(
+            "  ~(  (t >> 4)\n",
+            "    * (t & 255))\n",
+            "| t * 2\n",
+        )
+    );
+}
+
+#[test]
+fn formatted_parses_back_test() {
+    for &width in &[1, 10, 20, 40, 80] {
+        for e in &[PLAYER, "(t * 9 & t >> 4 | t * 5 & t >> 7 | t * 3 & t / 1024) - 1", "-(t-(1-2))-3"] {
+            let expression = parse(e).unwrap();
+            let text = format(&expression, &Config { width, keep_comments: true });
+            assert_eq!(parse(&text), Ok(expression), "{}", text);
+        }
+    }
+}
+
+#[test]
+fn comments_test() {
+    let source = "// kick\nt*5&t>>7 // melody\n| t>>4 // bass";
+    let (_, comments) = parse_with_comments(source).unwrap();
+    assert_eq!(
+        comments,
+        vec![
+            Comment { text: " kick".to_owned(), leaves_before: 0 },
+            Comment { text: " melody".to_owned(), leaves_before: 4 },
+            Comment { text: " bass".to_owned(), leaves_before: 6 },
+        ]
+    );
+
+    let config = Config { width: 16, keep_comments: true };
+    let formatted = format_source(source, &config).unwrap();
+    assert_eq!(
+        formatted,
+        concat!(
+            "  // kick\n",
+            "  t * 5 & t >> 7\n",
+            "// melody\n",
+            "| t >> 4\n",
+            "// bass\n",
+        )
+    );
+    assert_eq!(check(&formatted, &config), Ok(Check::Formatted));
+
+    let config = Config { width: 16, keep_comments: false };
+    assert_eq!(format_source(source, &config).unwrap(), "  t * 5 & t >> 7\n| t >> 4\n");
+}
+
+#[test]
+fn check_test() {
+    let config = Config { width: 40, keep_comments: true };
+    let formatted = format_source(PLAYER, &config).unwrap();
+    assert_eq!(check(&formatted, &config), Ok(Check::Formatted));
+    assert_eq!(check(PLAYER, &config), Ok(Check::Unformatted { line: 1 }));
+    assert_eq!(check(formatted.trim_end(), &config), Ok(Check::Unformatted { line: 3 }));
+
+    let edited = formatted.replace("| t >> 7", "|t >> 7");
+    assert_eq!(check(&edited, &config), Ok(Check::Unformatted { line: 3 }));
+    assert!(check("t +", &config).is_err());
+}
@@ -0,0 +1,104 @@
+extern crate bbb_core;
+
+use bbb_core::eval::eval;
+use bbb_core::jit::compile;
+#[cfg(feature = "jit")]
+use bbb_core::expr::Expr;
+#[cfg(feature = "jit")]
+use bbb_core::ops::UnOp;
+#[cfg(feature = "jit")]
+use bbb_core::optimize::optimize;
+use bbb_core::parser::parse;
+#[cfg(feature = "jit")]
+use bbb_core::parser::MAX_DEPTH;
+
+fn assert_matches_eval(e: &str, t_start: i32, len: usize) {
+    let expression = parse(e).unwrap();
+    let compiled = compile(&expression);
+    let mut out = vec![0; len];
+    let mut failed = vec![false; len];
+    compiled.eval_block(t_start, &mut out, &mut failed);
+
+    for i in 0..len {
+        let t = t_start + i as i32;
+        match eval(t, &expression) {
+            Ok(x) => assert_eq!((out[i], failed[i]), (x, false), "{} at t = {}", e, t),
+            Err(_) => assert_eq!((out[i], failed[i]), (0, true), "{} at t = {}", e, t),
+        }
+    }
+}
+
+#[test]
+fn block_matches_eval() {
+    assert_matches_eval("(t * 9 & t >> 4 | t * 5 & t >> 7 | t * 3 & t / 1024) - 1", 0, 1 << 16);
+    assert_matches_eval("((t<<1)^((t<<1)+(t>>7)&t>>12))|t>>(4-(1^7&(t>>19)))|t>>7", 0, 1 << 16);
+    assert_matches_eval("-t ^ ~(t >> 3) | !t", -1024, 2048);
+    assert_matches_eval("1.5 * t + 2.9", 0, 256);
+}
+
+#[test]
+fn division_by_zero_fails_per_sample() {
+    assert_matches_eval("t / (t >> 8 & 3)", 0, 4096);
+    assert_matches_eval("1 / 0", 0, 16);
+}
+
+#[test]
+fn overflow_wraps_or_fails_per_sample() {
+    // with or without the `jit` feature, so the interpreter is checked too
+    let compiled = compile(&parse("(~2147483647 + t) / -1").unwrap());
+    let mut out = [7; 3];
+    let mut failed = [false; 3];
+    compiled.eval_block(0, &mut out, &mut failed);
+    assert_eq!(failed, [true, false, false]);
+    assert_eq!(out, [0, i32::MAX, i32::MAX - 1]);
+
+    let compiled = compile(&parse("t + 2147483647").unwrap());
+    compiled.eval_block(0, &mut out, &mut failed);
+    assert_eq!(failed, [false; 3]);
+    assert_eq!(out, [i32::MAX, i32::MIN, i32::MIN + 1]);
+}
+
+#[test]
+fn empty_block() {
+    let compiled = compile(&parse("t").unwrap());
+    compiled.eval_block(0, &mut [], &mut []);
+}
+
+#[cfg(feature = "jit")]
+#[test]
+fn compiles_natively() {
+    assert!(compile(&parse("t * 5 & t >> 7").unwrap()).is_native());
+}
+
+#[cfg(feature = "jit")]
+#[test]
+fn compiles_negative_constants() {
+    // folding leaves negative literals, which the parser never produces
+    let folded = optimize(&parse("t + -5 & -1.5").unwrap());
+    let compiled = compile(&folded);
+    assert_eq!(compiled.fallback_reason(), None);
+
+    let mut out = [0; 3];
+    let mut failed = [false; 3];
+    compiled.eval_block(0, &mut out, &mut failed);
+    assert_eq!(out, [-5, -4, -3]);
+}
+
+#[cfg(feature = "jit")]
+#[test]
+fn deep_expressions_fall_back() {
+    // one operator more than the parser accepts, so it can only be built
+    // by hand
+    let mut deep = Expr::Time;
+    for _ in 0..MAX_DEPTH + 1 {
+        deep = Expr::UnExpr(UnOp::BitNot, Box::new(deep));
+    }
+    let compiled = compile(&deep);
+    assert!(!compiled.is_native());
+
+    let mut out = [0; 2];
+    let mut failed = [false; 2];
+    compiled.eval_block(0, &mut out, &mut failed);
+    // an odd number of `~`
+    assert_eq!(out, [!0, !1]);
+}
@@ -0,0 +1,88 @@
+extern crate bbb_core;
+
+use bbb_core::limits::*;
+use bbb_core::parser::{parse, parse_with_limits};
+#[cfg(feature = "audio")]
+use bbb_core::signal::ExprSignal;
+#[cfg(feature = "audio")]
+use bbb_core::wav::{self, Recorder};
+
+fn small() -> Limits {
+    Limits {
+        max_source_len: 64,
+        max_depth: 8,
+        max_nodes: 16,
+        max_ops_per_sample: 6,
+        max_duration: 1.0,
+    }
+}
+
+#[test]
+fn accepts_expressions_within_limits() {
+    assert_eq!(parse_with_limits("t * 5 & t >> 7", &small()), parse("t * 5 & t >> 7").map_err(Error::Syntax));
+    assert!(parse_with_limits("((t<<1)^((t<<1)+(t>>7)&t>>12))|t>>(4-(1^7&(t>>19)))|t>>7", &Limits::default()).is_ok());
+    // redundant parentheses don't make the tree any deeper
+    assert_eq!(parse_with_limits("(((((((((t)))))))))", &small()), Ok(parse("t").unwrap()));
+}
+
+#[test]
+fn reports_each_violation() {
+    assert_eq!(
+        parse_with_limits(&"t".repeat(65), &small()),
+        Err(Error::SourceTooLong { len: 65, max: 64 })
+    );
+
+    assert_eq!(
+        parse_with_limits("~~~~~~~~t", &small()),
+        Err(Error::TooDeep { depth: 9, max: 8 })
+    );
+
+    assert_eq!(
+        parse_with_limits("t*t&t*t|t*t&t*t^t", &small()),
+        Err(Error::TooManyNodes { nodes: 17, max: 16 })
+    );
+
+    assert_eq!(
+        parse_with_limits("t*t&t*t|t*t&t*t", &small()),
+        Err(Error::TooManyOps { ops: 7, max: 6 })
+    );
+
+    match parse_with_limits("t +", &small()) {
+        Err(Error::Syntax(_)) => {}
+        other => panic!("expected a syntax error, got {:?}", other),
+    }
+}
+
+#[test]
+#[cfg(feature = "audio")]
+fn signal_checks_op_budget() {
+    let e = parse("t*t&t*t|t*t&t*t").unwrap();
+    assert!(ExprSignal::with_limits(e.clone(), &small()).is_err());
+    assert!(ExprSignal::with_limits(e, &Limits::default()).is_ok());
+}
+
+#[test]
+#[cfg(feature = "audio")]
+fn signal_checks_every_limit() {
+    let deep = parse("~~~~~~~~~t").unwrap();
+    assert_eq!(
+        ExprSignal::with_limits(deep, &small()).err(),
+        Some(Error::TooDeep { depth: 10, max: 8 })
+    );
+    let wide = parse("t*t&t*t|t*t&t*t^t").unwrap();
+    assert_eq!(
+        ExprSignal::with_limits(wide, &small()).err(),
+        Some(Error::TooManyNodes { nodes: 17, max: 16 })
+    );
+}
+
+#[test]
+#[cfg(feature = "audio")]
+fn recorder_checks_duration() {
+    let recorder = Recorder::with_limits(8_000, small());
+    let mut signal = ExprSignal::from(parse("t").unwrap());
+    match recorder.record("never_written.wav", 2.0, &mut signal) {
+        Err(wav::Error::Limit(e)) => assert_eq!(e, Error::DurationTooLong { duration: 2.0, max: 1.0 }),
+        other => panic!("expected the duration limit, got {:?}", other),
+    }
+}
@@ -0,0 +1,120 @@
+extern crate bbb_core;
+
+use bbb_core::lint::*;
+use bbb_core::parser::{parse, Span};
+
+fn codes(e: &str) -> Vec<Code> {
+    lint(&parse(e).unwrap()).iter().map(|lint| lint.code).collect()
+}
+
+#[test]
+fn clean_test() {
+    assert_eq!(codes("t*5&t>>7"), vec![]);
+    assert_eq!(codes("((t<<1)^((t<<1)+(t>>7)&t>>12))|t>>(4-(1^7&(t>>19)))|t>>7"), vec![]);
+    assert_eq!(codes("t*2.0"), vec![]);
+}
+
+#[test]
+fn division_by_zero_test() {
+    let lints = lint(&parse("t>>4|t/0").unwrap());
+    assert_eq!(lints.len(), 1);
+    assert_eq!(lints[0].code, Code::DivisionByZero);
+    assert_eq!(lints[0].severity, Severity::Error);
+    assert_eq!(lints[0].path, vec![1]);
+    assert_eq!(lints[0].fix, None);
+    assert_eq!(lints[0].to_string(), "error[L001]: `t/0` divides by 0, which always fails");
+}
+
+#[test]
+fn shift_out_of_range_test() {
+    let e = parse("t*5>>33").unwrap();
+    let lints = lint(&e);
+    assert_eq!(lints.len(), 1);
+    assert_eq!(lints[0].code, Code::ShiftOutOfRange);
+    assert_eq!(lints[0].severity, Severity::Error);
+    assert_eq!(lints[0].apply(&e), Some(parse("t*5>>1").unwrap()));
+    assert_eq!(codes("t<<-1"), vec![Code::ShiftOutOfRange]);
+}
+
+#[test]
+fn no_op_test() {
+    assert_eq!(codes("t*1"), vec![Code::NoOp]);
+    assert_eq!(codes("0+t"), vec![Code::NoOp]);
+    assert_eq!(codes("t|0"), vec![Code::NoOp]);
+    assert_eq!(codes("t&-1"), vec![Code::NoOp]);
+
+    let e = parse("(t>>4)*1&t").unwrap();
+    let lints = lint(&e);
+    assert_eq!(lints[0].path, vec![0]);
+    let fix = lints[0].fix.clone().unwrap();
+    assert_eq!(fix.text, "t>>4");
+    assert_eq!(lints[0].apply(&e), Some(parse("(t>>4)&t").unwrap()));
+
+    // the replacement needs parentheses where the node was
+    let e = parse("t*(t+0)").unwrap();
+    let fix = lint(&e)[0].fix.clone().unwrap();
+    assert_eq!(fix.text, "t");
+    let e = parse("t*(t|0)").unwrap();
+    assert_eq!(lint(&e)[0].fix.clone().unwrap().text, "t");
+    let e = parse("~((t+1)*1)").unwrap();
+    assert_eq!(lint(&e)[0].fix.clone().unwrap().text, "(t+1)");
+}
+
+#[test]
+fn masked_away_test() {
+    let e = parse("(t>>3)&0|t>>4").unwrap();
+    let lints = lint(&e);
+    assert_eq!(lints.len(), 1);
+    assert_eq!(lints[0].code, Code::MaskedAway);
+    assert_eq!(lints[0].message, "`t>>3&0` is 0 for every `t`");
+    assert_eq!(lints[0].apply(&e), Some(parse("0|t>>4").unwrap()));
+
+    // `t*3` may overflow, which dropping it would hide
+    let lints = lint(&parse("(t*3)&0|t>>4").unwrap());
+    assert_eq!(lints[0].code, Code::MaskedAway);
+    assert_eq!(lints[0].fix, None);
+
+    assert_eq!(codes("(t<<8&255)+t"), vec![Code::MaskedAway]);
+    assert_eq!(codes("t|(t|-1)"), vec![Code::MaskedAway]);
+    assert_eq!(codes("-(t|-1)"), vec![Code::ConstantOutput, Code::MaskedAway]);
+    // a constant has nothing to mask
+    assert_eq!(codes("5&0|t"), vec![]);
+}
+
+#[test]
+fn constant_output_test() {
+    let lints = lint(&parse("t<<8").unwrap());
+    assert_eq!(lints.len(), 1);
+    assert_eq!(lints[0].code, Code::ConstantOutput);
+    assert_eq!(lints[0].fix, None);
+
+    let lints = lint(&parse("(1+2)*3").unwrap());
+    assert_eq!(lints[0].code, Code::ConstantOutput);
+    assert_eq!(lints[0].fix.clone().unwrap().text, "9");
+
+    // reported once, as the root is masked away
+    assert_eq!(codes("t*3&0"), vec![Code::MaskedAway]);
+}
+
+#[test]
+fn truncated_float_test() {
+    let e = parse("t*2.5").unwrap();
+    let lints = lint(&e);
+    assert_eq!(lints.len(), 1);
+    assert_eq!(lints[0].code, Code::TruncatedFloat);
+    assert_eq!(lints[0].message, "`2.5` is truncated to 2");
+    assert_eq!(lints[0].apply(&e), Some(parse("t*2").unwrap()));
+}
+
+#[test]
+fn spans_test() {
+    let source = "t * 1 | (t / 0)";
+    let lints = lint_source(source).unwrap();
+    let spans: Vec<Span> = lints.iter().map(|lint| lint.span.unwrap()).collect();
+    assert_eq!(spans, vec![Span { start: 0, end: 5 }, Span { start: 9, end: 14 }]);
+    assert_eq!(
+        lints[0].to_string(),
+        "warning[L003]: `t*1` does nothing at 0..5 (replace with `t`)"
+    );
+    assert!(lint_source("t +").is_err());
+}
@@ -0,0 +1,87 @@
+extern crate bbb_core;
+
+use bbb_core::equivalence::{Checker, Verdict};
+use bbb_core::minify::*;
+use bbb_core::parser::parse;
+use bbb_core::range::Interval;
+
+fn minified(e: &str) -> String {
+    minify(&parse(e).unwrap())
+}
+
+#[test]
+fn whitespace_and_parentheses_test() {
+    assert_eq!(minified("( t * 5 ) & ( t >> 7 )"), "t*5&t>>7");
+    assert_eq!(minified("t - (1 - t)"), "t-1-t");
+    assert_eq!(minified("~(t >> 4)"), "~(t>>4)");
+}
+
+#[test]
+fn literals_test() {
+    assert_eq!(minified("t*2.5"), "t*2");
+    assert_eq!(minified("t|-10"), "t|~9");
+    assert_eq!(minified("t^-1"), "t^-1");
+    assert_eq!(minified("t|1048576"), "t|1<<20");
+    assert_eq!(minified("t*1048576"), "t*1048576");
+    assert_eq!(minified("t&(1<<31)"), "t&1<<31");
+    assert_eq!(minified("t>>(2+2)"), "t>>4");
+}
+
+#[test]
+fn identities_test() {
+    assert_eq!(minified("t*1+0"), "t");
+    assert_eq!(minified("t+-5"), "t-5");
+    assert_eq!(minified("t - -5"), "t+5");
+    assert_eq!(minified("-5+t"), "t-5");
+    assert_eq!(minified("(t-1)+t"), "t+t-1");
+    assert_eq!(minified("(t*3)*t"), "t*t*3");
+    assert_eq!(minified("((t&1)&t)&t>>4"), "t&1&t&t>>4");
+    assert_eq!(minified("(t|1)^t"), "(t|1)^t");
+    // regrouping could overflow in between, so `+` and `*` chains stay
+    assert_eq!(minified("(t+1)+(t+1)"), "(t+1)+t+1");
+    // `1/0` still fails
+    assert_eq!(minified("t*(0*(1/0))"), "t*0*1/0");
+}
+
+#[test]
+fn overflowing_input_test() {
+    // `t*(t>>5)` overflows for large `t`, which plays as it wraps
+    assert_eq!(minified("t*(t>>5)+-5"), "t*(t>>5)-5");
+}
+
+#[test]
+fn long_chains_test() {
+    // every operand of a commutative chain can be swapped, which mustn't
+    // multiply the work
+    let sum = vec!["t"; 200].join("+");
+    assert_eq!(golf(&parse(&sum).unwrap()), parse(&sum).unwrap());
+    let product = vec!["(t>>4)"; 100].join("*");
+    let golfed = golf(&parse(&product).unwrap());
+    assert_eq!(golfed, parse(&product).unwrap());
+}
+
+#[test]
+fn same_output_test() {
+    let checker = Checker {
+        exhaustive: Interval::new(0, (1 << 12) - 1),
+        samples: 1 << 10,
+        sampled: Interval::new(0, (1 << 20) - 1),
+    };
+    let examples = [
+        "((t<<1)^((t<<1)+(t>>7)&t>>12))|t>>(4-(1^7&(t>>19)))|t>>7",
+        "(t * 9 & t >> 4 | t * 5 & t >> 7 | t * 3 & t / 1024) - 1",
+        "t*(((t>>12)|(t>>8))&(63&(t>>4)))",
+        "(t>>6|t|t>>(t>>16))*10+((t>>11)&7)",
+        "-(~(t>>3))+-4096",
+    ];
+    for e in &examples {
+        let expression = parse(e).unwrap();
+        let text = minify_with(&expression, &checker);
+        assert!(text.len() <= e.len(), "{}", text);
+        let reparsed = parse(&text).unwrap();
+        assert_eq!(reparsed, golf(&expression));
+        if let Verdict::Counterexample { .. } = checker.check(&expression, &reparsed) {
+            panic!("{} plays differently from {}", text, e);
+        }
+    }
+}
@@ -15,9 +15,15 @@ fn multi_digit_int() {
 }
 
 #[test]
-fn negative_int() {
+fn unsigned_int() {
     let string = "-100".as_bytes();
-    assert_eq!(number(string).to_result().unwrap(), Numeral::Int(-100));
+    assert!(number(string).is_err());
+}
+
+#[test]
+fn out_of_range_int() {
+    let string = "2147483648".as_bytes();
+    assert!(number(string).is_err());
 }
 
 #[test]
@@ -33,7 +39,7 @@ fn leading_zero_float() {
 }
 
 #[test]
-fn negative_float() {
+fn unsigned_float() {
     let string = "-1.0001".as_bytes();
-    assert_eq!(number(string).to_result().unwrap(), Numeral::Float(-1.0001));
+    assert!(number(string).is_err());
 }
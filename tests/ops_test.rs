@@ -2,7 +2,6 @@ extern crate nom;
 extern crate bbb_core;
 
 use bbb_core::ops::*;
-use nom::Needed::Size;
 use nom::IResult::*;
 
 #[test]
@@ -14,7 +13,12 @@ fn unary_parse() {
 
     assert_eq!(
         unop("-".as_bytes()),
-        Incomplete(Size(2))
+        Done(&b""[..], UnOp::Neg)
+    );
+
+    assert_eq!(
+        unop("- 5".as_bytes()),
+        Done(&b" 5"[..], UnOp::Neg)
     );
 
     assert_eq!(
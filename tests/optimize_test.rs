@@ -0,0 +1,63 @@
+extern crate bbb_core;
+
+use bbb_core::eval::eval;
+use bbb_core::expr::Expr::*;
+use bbb_core::numeral::Numeral::*;
+use bbb_core::ops::*;
+use bbb_core::optimize::optimize;
+use bbb_core::parser::parse;
+
+fn optimized(e: &str) -> bbb_core::expr::Expr {
+    optimize(&parse(e).unwrap())
+}
+
+#[test]
+fn folds_constants() {
+    assert_eq!(optimized("(2*4)"), Num(Int(8)));
+    assert_eq!(optimized("1 + 2 * 3 >> 1"), Num(Int(3)));
+    assert_eq!(optimized("~0 | 1.5"), Num(Int(-1)));
+    assert_eq!(
+        optimized("t * (3 - 1)"),
+        BinExpr(Box::new(Time), BinOp::One(BinOp1::Mul), Box::new(Num(Int(2))))
+    );
+}
+
+#[test]
+fn strips_identities() {
+    assert_eq!(optimized("(t*1)+0"), Time);
+    assert_eq!(optimized("t>>0"), Time);
+    assert_eq!(optimized("0 | t ^ 0"), Time);
+    assert_eq!(optimized("~~t"), Time);
+    assert_eq!(optimized("(t >> 3) & 0"), Num(Int(0)));
+    assert_eq!(optimized("(t >> 3) ^ (t >> 3)"), Num(Int(0)));
+}
+
+#[test]
+fn keeps_failing_subexpressions() {
+    assert_eq!(optimized("1/0"), parse("1/0").unwrap());
+    assert_eq!(optimized("(t/0) * 0"), parse("(t/0) * 0").unwrap());
+    assert_eq!(optimized("(t/(t&1)) - (t/(t&1))"), parse("(t/(t&1)) - (t/(t&1))").unwrap());
+}
+
+#[test]
+fn keeps_overflowing_subexpressions() {
+    assert_eq!(optimized("2147483647 + 1"), parse("2147483647 + 1").unwrap());
+    assert_eq!(optimized("1 << 32"), parse("1 << 32").unwrap());
+}
+
+#[test]
+fn output_is_unchanged() {
+    let expressions = [
+        "(t * 9 & t >> 4 | t * 5 & t >> 7 | t * 3 & t / 1024) - 1",
+        "((t<<1)^((t<<1)+(t>>7)&t>>12))|t>>(4-(1^7&(t>>19)))|t>>7",
+        "(t*1+0)*(t>>(2*4)&7|0) / (t & 3)",
+    ];
+
+    for e in expressions.iter() {
+        let original = parse(e).unwrap();
+        let optimized = optimize(&original);
+        for t in 0..1 << 16 {
+            assert_eq!(eval(t, &optimized), eval(t, &original), "{} at t = {}", e, t);
+        }
+    }
+}
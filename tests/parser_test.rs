@@ -17,19 +17,19 @@ fn number_parse_test() {
     let e = "-1";
     assert_eq!(
         parse(e),
-        Ok(Num(Int(-1)))
+        Ok(UnExpr(Neg, Box::new(Num(Int(1)))))
     );
 
     let e = " -10 ";
     assert_eq!(
         parse(e),
-        Ok(Num(Int(-10)))
+        Ok(UnExpr(Neg, Box::new(Num(Int(10)))))
     );
 
     let e = "(-10)";
     assert_eq!(
         parse(e),
-        Ok(Num(Int(-10)))
+        Ok(UnExpr(Neg, Box::new(Num(Int(10)))))
     );
 }
 
@@ -75,6 +75,51 @@ fn unary_parse_test() {
     );
 }
 
+#[test]
+fn unary_minus_parse_test() {
+    let neg = |e| UnExpr(Neg, Box::new(e));
+    let sub = |a, b| BinExpr(Box::new(a), BinOp::Two(BinOp2::Sub), Box::new(b));
+
+    // a `-` before a literal negates it like anything else
+    let five = || Num(Int(5));
+    for e in &["-5", "- 5", "-(5)", "(-5)", "-\n5"] {
+        assert_eq!(parse(e), Ok(neg(five())), "{}", e);
+    }
+    assert_eq!(parse("-\t2.5"), Ok(neg(Num(Float(2.5)))));
+    for e in &["--5", "- -5", "-(-5)", "- - 5"] {
+        assert_eq!(parse(e), Ok(neg(neg(five()))), "{}", e);
+    }
+
+    assert_eq!(parse("--t"), Ok(neg(neg(Time))));
+    assert_eq!(parse("- - t"), Ok(neg(neg(Time))));
+    assert_eq!(parse("-(-t)"), Ok(neg(neg(Time))));
+    assert_eq!(parse("-~-t"), Ok(neg(UnExpr(BitNot, Box::new(neg(Time))))));
+
+    // after an operand a `-` is binary, however it is spaced
+    for e in &["t-1", "t -1", "t- 1", "t - 1"] {
+        assert_eq!(parse(e), Ok(sub(Time, Num(Int(1)))), "{}", e);
+    }
+    for e in &["t--1", "t - -1", "t- -1", "t -- 1", "t - - 1"] {
+        assert_eq!(parse(e), Ok(sub(Time, neg(Num(Int(1))))), "{}", e);
+    }
+    assert_eq!(parse("t---t"), Ok(sub(Time, neg(neg(Time)))));
+
+    // prefix operators bind tighter than any binary operator
+    let e = parse("-t*5").unwrap();
+    assert_eq!(e, BinExpr(Box::new(neg(Time)), BinOp::One(BinOp1::Mul), Box::new(Num(Int(5)))));
+    let e = parse("-5*2").unwrap();
+    assert_eq!(e, BinExpr(Box::new(neg(five())), BinOp::One(BinOp1::Mul), Box::new(Num(Int(2)))));
+    let e = parse("t*-t").unwrap();
+    assert_eq!(e, BinExpr(Box::new(Time), BinOp::One(BinOp1::Mul), Box::new(neg(Time))));
+
+    // the magnitude of `i32::MIN` is too big for a literal
+    assert!(parse("2147483648").is_err());
+    assert!(parse("-2147483648").is_err());
+    assert_eq!(parse("-2147483647"), Ok(neg(Num(Int(i32::MAX)))));
+    assert_eq!(parse("-"), Err("incomplete expression".to_owned()));
+    assert_eq!(parse("t -"), Err("incomplete expression".to_owned()));
+}
+
 #[test]
 fn simple_binary_parse_test() {
     let e = "1 + 1";
@@ -216,3 +261,202 @@ fn complex_binary_parse_test() {
         )
     );
 }
+
+#[test]
+fn same_level_groups_right_test() {
+    let e = "t - 1 - 2";
+    assert_eq!(
+        parse(e),
+        Ok(
+            BinExpr(
+                Box::new(Time),
+                BinOp::Two(BinOp2::Sub),
+                Box::new(BinExpr(
+                    Box::new(Num(Int(1))),
+                    BinOp::Two(BinOp2::Sub),
+                    Box::new(Num(Int(2)))
+                ))
+            )
+        )
+    );
+
+    let e = "-(t) * ~t";
+    assert_eq!(
+        parse(e),
+        Ok(
+            BinExpr(
+                Box::new(UnExpr(Neg, Box::new(Time))),
+                BinOp::One(BinOp1::Mul),
+                Box::new(UnExpr(BitNot, Box::new(Time)))
+            )
+        )
+    );
+}
+
+#[test]
+fn invalid_parse_test() {
+    assert!(parse("").is_err());
+    assert!(parse("t +").is_err());
+    assert!(parse("(t").is_err());
+    assert!(parse("t)").is_err());
+    assert!(parse("t t").is_err());
+    assert!(parse("t * / 2").is_err());
+}
+
+#[test]
+fn deep_nesting_parse_test() {
+    let n = 100_000;
+
+    let e = format!("{}t{}", "(".repeat(n), ")".repeat(n));
+    assert_eq!(parse(&e), Ok(Time));
+
+    let e = format!("{}t", "~".repeat(n));
+    assert!(parse(&e).is_err());
+
+    let e = format!("{}t{}", "(t+".repeat(n), ")".repeat(n));
+    assert!(parse(&e).is_err());
+
+    let e = format!("{}t", "~".repeat(MAX_DEPTH - 1));
+    assert!(parse(&e).is_ok());
+}
+
+#[test]
+fn long_chain_parse_test() {
+    let e = format!("t{}", " + t".repeat(1000));
+    assert!(parse(&e).is_ok());
+
+    let e = format!("t{}", "|t^t&t".repeat(300));
+    assert!(parse(&e).is_ok());
+
+    let e = format!("t{}", "+t".repeat(100_000));
+    assert!(parse(&e).is_err());
+
+    // every operator of a chain counts towards the depth
+    let e = format!("t{}", "+t".repeat(MAX_DEPTH - 1));
+    assert!(parse(&e).is_ok());
+    let e = format!("t{}", "+t".repeat(MAX_DEPTH));
+    assert_eq!(
+        parse(&e),
+        Err(format!(
+            "expression is more than {} operators deep, counting each operator of a chain such as `t+t+t`",
+            MAX_DEPTH
+        ))
+    );
+}
+
+#[test]
+fn comments_parse_test() {
+    let commented = |e| parse_with_comments(e).map(|(expression, _)| expression);
+    assert_eq!(commented("// time\nt"), Ok(Time));
+    assert_eq!(commented("t // time"), Ok(Time));
+    assert_eq!(
+        commented("t >> // shift\n 4"),
+        Ok(BinExpr(Box::new(Time), BinOp::Three(BitShift::Right), Box::new(Num(Int(4)))))
+    );
+    assert!(commented("t / / 2").is_err());
+    assert!(commented("// t").is_err());
+    assert_eq!(
+        parse_with_comments("t // a\n* 2 //b"),
+        Ok((
+            BinExpr(Box::new(Time), BinOp::One(BinOp1::Mul), Box::new(Num(Int(2)))),
+            vec![
+                Comment { text: " a".to_owned(), leaves_before: 1 },
+                Comment { text: "b".to_owned(), leaves_before: 2 },
+            ]
+        ))
+    );
+}
+
+#[test]
+fn no_comments_parse_test() {
+    // only `parse_with_comments` reads comments
+    assert!(parse("// time\nt").is_err());
+    assert!(parse("t // time").is_err());
+    assert!(parse_with_spans("t // time").is_err());
+    match parse_partial("t // time") {
+        Partial::Invalid { offset: 3, .. } => {}
+        partial => panic!("{:?}", partial),
+    }
+}
+
+#[test]
+fn spans_parse_test() {
+    let source = "-(t * 5) & t>>7";
+    let (expression, spans) = parse_with_spans(source).unwrap();
+    let texts: Vec<&str> = spans.iter().map(|span| &source[span.start..span.end]).collect();
+    assert_eq!(texts, vec!["-(t * 5) & t>>7", "-(t * 5)", "t * 5", "t", "5", "t>>7", "t", "7"]);
+    assert_eq!(spans.len(), expression.node_count());
+
+    let source = "t*- 5";
+    let (_, spans) = parse_with_spans(source).unwrap();
+    let texts: Vec<&str> = spans.iter().map(|span| &source[span.start..span.end]).collect();
+    assert_eq!(texts, vec!["t*- 5", "t", "- 5", "5"]);
+
+    let (_, spans) = parse_with_spans(" ((t)) ").unwrap();
+    assert_eq!(spans, vec![Span { start: 3, end: 4 }]);
+
+    let source = "(t*5+1)/~(t>>2)";
+    let (_, spans) = parse_with_spans(source).unwrap();
+    let texts: Vec<&str> = spans.iter().map(|span| &source[span.start..span.end]).collect();
+    assert_eq!(texts, vec![source, "t*5+1", "t*5", "t", "5", "1", "~(t>>2)", "t>>2", "t", "2"]);
+
+    let source = format!("t{}", "+t".repeat(MAX_DEPTH - 1));
+    let (expression, spans) = parse_with_spans(&source).unwrap();
+    assert_eq!(spans.len(), expression.node_count());
+    assert_eq!(spans[1], Span { start: 0, end: 1 });
+    assert_eq!(spans[2], Span { start: 2, end: source.len() });
+}
+
+#[test]
+fn partial_parse_test() {
+    assert_eq!(parse_partial("t*(5&t)"), Partial::Complete(parse("t*(5&t)").unwrap()));
+
+    let incomplete = |e| match parse_partial(e) {
+        Partial::Incomplete { expected } => expected,
+        partial => panic!("{:?}", partial),
+    };
+    assert_eq!(incomplete(""), Expected::Operand);
+    assert_eq!(incomplete("t*(5&"), Expected::Operand);
+    assert_eq!(incomplete("~ \n"), Expected::Operand);
+    assert_eq!(incomplete("t*(5&t"), Expected::Operator { open_parens: 1 });
+    assert_eq!(incomplete("((t"), Expected::Operator { open_parens: 2 });
+    assert_eq!(incomplete("t|-"), Expected::Operand);
+    assert_eq!(incomplete("t<"), Expected::Shift(BitShift::Left));
+    assert_eq!(incomplete("t >"), Expected::Shift(BitShift::Right));
+    assert_eq!(parse("t<"), Err("incomplete expression".to_owned()));
+
+    assert_eq!(
+        parse_partial("t*(5&)"),
+        Partial::Invalid {
+            offset: 5,
+            expected: Some(Expected::Operand),
+            message: "expected `t`, a number, `(` or a unary operator at offset 5".to_owned(),
+        }
+    );
+    match parse_partial("(t $") {
+        Partial::Invalid { offset, expected, .. } => {
+            assert_eq!(offset, 3);
+            assert_eq!(expected, Some(Expected::Operator { open_parens: 1 }));
+        }
+        partial => panic!("{:?}", partial),
+    }
+    match parse_partial("t)") {
+        Partial::Invalid { expected, message, .. } => {
+            assert_eq!(expected, Some(Expected::Operator { open_parens: 0 }));
+            assert_eq!(message, "unmatched `)` at offset 1");
+        }
+        partial => panic!("{:?}", partial),
+    }
+    match parse_partial(&format!("{}t", "~".repeat(MAX_DEPTH))) {
+        Partial::Invalid { expected, .. } => assert_eq!(expected, None),
+        partial => panic!("{:?}", partial),
+    }
+}
+
+#[test]
+fn expected_hint_test() {
+    assert_eq!(Expected::Operand.to_string(), "`t`, a number, `(` or a unary operator");
+    assert_eq!(Expected::Shift(BitShift::Right).to_string(), "`>`");
+    assert_eq!(Expected::Operator { open_parens: 0 }.to_string(), "a binary operator or the end");
+    assert_eq!(Expected::Operator { open_parens: 2 }.to_string(), "a binary operator or `)`");
+}
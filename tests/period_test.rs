@@ -0,0 +1,73 @@
+extern crate bbb_core;
+
+use bbb_core::eval::eval;
+use bbb_core::parser::parse;
+use bbb_core::period::*;
+
+fn period_of(e: &str, bound: usize) -> Option<Period> {
+    period(&parse(e).unwrap(), bound)
+}
+
+#[test]
+fn analytic_period_test() {
+    assert_eq!(analytic_period(&parse("42").unwrap()), Some(1));
+    assert_eq!(analytic_period(&parse("t").unwrap()), Some(256));
+    assert_eq!(analytic_period(&parse("t * 3 + 1").unwrap()), Some(256));
+    assert_eq!(analytic_period(&parse("t >> 4").unwrap()), Some(4096));
+    assert_eq!(analytic_period(&parse("(t >> 4) & 3").unwrap()), Some(64));
+    assert_eq!(analytic_period(&parse("t << 2").unwrap()), Some(64));
+    assert_eq!(analytic_period(&parse("t | 255").unwrap()), Some(1));
+    assert_eq!(analytic_period(&parse("t * (t >> 10)").unwrap()), Some(1 << 18));
+    assert_eq!(analytic_period(&parse("t / 3").unwrap()), None);
+    assert_eq!(analytic_period(&parse("t >> (t >> 8)").unwrap()), None);
+}
+
+#[test]
+fn failures_count_as_dependencies_test() {
+    // masking the quotient away doesn't hide the division by zero
+    assert_eq!(analytic_period(&parse("(t & 15) + (1 / (t >> 12) & 0)").unwrap()), None);
+}
+
+#[test]
+fn exact_period_test() {
+    assert_eq!(period_of("42", 100), Some(Period::Exact(1)));
+    assert_eq!(period_of("t & 7", 100), Some(Period::Exact(8)));
+    assert_eq!(period_of("t * 3", 1000), Some(Period::Exact(256)));
+    assert_eq!(period_of("(t >> 4) & 1", 1000), Some(Period::Exact(32)));
+    // an upper bound of 256, but the output repeats sooner
+    assert_eq!(period_of("t * 64", 1000), Some(Period::Exact(4)));
+}
+
+#[test]
+fn observed_period_test() {
+    // `t >> 31` is 0 for every rendered `t`, but that takes more than bits to see
+    assert_eq!(period_of("t & 15 ^ t >> 31", 100), Some(Period::Observed(16)));
+    assert_eq!(period_of("t / 3", 100), None);
+    assert_eq!(period_of("t & 1023", 100), None);
+}
+
+#[test]
+fn observed_period_of_samples_test() {
+    assert_eq!(observed_period::<i8>(&[]), None);
+    assert_eq!(observed_period(&[1]), None);
+    assert_eq!(observed_period(&[1, 1]), Some(1));
+    assert_eq!(observed_period(&[1, 2, 3, 1, 2, 3, 1]), Some(3));
+    assert_eq!(observed_period(&[1, 2, 3, 1, 2]), None);
+    assert_eq!(observed_period(&[1, 2, 1, 1, 2, 1]), Some(3));
+}
+
+#[test]
+fn period_matches_eval_test() {
+    for e in &["t & 63", "(t >> 3) ^ (t >> 5) & 127", "t * 5 & t >> 7"] {
+        let expression = parse(e).unwrap();
+        let p = match period(&expression, 1 << 16).unwrap() {
+            Period::Exact(p) => p as i32,
+            Period::Observed(_) => panic!("{} should have an exact period", e),
+        };
+        for t in 0..4096 {
+            let a = eval(t, &expression).map(|x| x as i8);
+            let b = eval(t + p, &expression).map(|x| x as i8);
+            assert_eq!(a, b, "{} at t = {}", e, t);
+        }
+    }
+}
@@ -0,0 +1,130 @@
+extern crate bbb_core;
+
+use bbb_core::eval::eval;
+use bbb_core::expr::Expr;
+use bbb_core::expr::Expr::*;
+use bbb_core::numeral::Numeral::*;
+use bbb_core::ops::*;
+use bbb_core::parser::parse;
+use bbb_core::printer::*;
+
+fn reprinted(e: &str, style: Style) -> String {
+    print(&parse(e).unwrap(), style)
+}
+
+#[test]
+fn minimal_parentheses_test() {
+    assert_eq!(reprinted("((t*5)&(t>>7))", Style::Compact), "t*5&t>>7");
+    assert_eq!(reprinted("t-(1-2)", Style::Compact), "t-1-2");
+    assert_eq!(reprinted("(t-1)-2", Style::Compact), "(t-1)-2");
+    assert_eq!(reprinted("(t+1)*2", Style::Compact), "(t+1)*2");
+    assert_eq!(reprinted("t|(t^(t&1))", Style::Compact), "t|t^t&1");
+    assert_eq!(reprinted("((t|t)^t)&1", Style::Compact), "((t|t)^t)&1");
+    assert_eq!(reprinted("~(t>>4)", Style::Compact), "~(t>>4)");
+    assert_eq!(reprinted("(~t)>>4", Style::Compact), "~t>>4");
+}
+
+#[test]
+fn unary_operands_test() {
+    assert_eq!(reprinted("-t", Style::Compact), "-t");
+    assert_eq!(reprinted("-(5)", Style::Compact), "-5");
+    assert_eq!(reprinted("-5", Style::Compact), "-5");
+    assert_eq!(reprinted("-(~t)", Style::Compact), "-~t");
+    assert_eq!(reprinted("-(-t)", Style::Compact), "--t");
+    assert_eq!(reprinted("-(-5)", Style::Compact), "--5");
+    assert_eq!(reprinted("-(5.5)", Style::Compact), "-5.5");
+    assert_eq!(reprinted("~(-t)", Style::Compact), "~-t");
+    assert_eq!(reprinted("!(5)", Style::Compact), "!5");
+    assert_eq!(reprinted("t - -1", Style::Compact), "t--1");
+    assert_eq!(reprinted("t-(-t)", Style::Compact), "t--t");
+}
+
+#[test]
+fn literals_test() {
+    assert_eq!(reprinted("1.5*t", Style::Compact), "1.5*t");
+    assert_eq!(reprinted("2.0*t", Style::Compact), "2.0*t");
+    assert_eq!(reprinted("2.*t", Style::Compact), "2.0*t");
+}
+
+#[test]
+fn negative_literals_test() {
+    // folding constants makes them, and they print as negations
+    let e = BinExpr(Box::new(Time), BinOp::Two(BinOp2::Sub), Box::new(Num(Int(-5))));
+    assert_eq!(print(&e, Style::Compact), "t--5");
+    assert_eq!(eval(7, &parse("t--5").unwrap()), Ok(12));
+    assert_eq!(print(&Num(Float(-2.5)), Style::Compact), "-2.5");
+    assert_eq!(print(&Num(Int(i32::MIN)), Style::Compact), "~2147483647");
+    assert_eq!(eval(0, &parse("~2147483647").unwrap()), Ok(i32::MIN));
+}
+
+#[test]
+fn styles_test() {
+    let e = parse("t*5&-(t>>7)|~t").unwrap();
+    assert_eq!(print(&e, Style::Spaced), "t * 5 & -(t >> 7) | ~t");
+    assert_eq!(e.to_string(), "t*5&-(t>>7)|~t");
+    assert_eq!(format!("{:#}", e), "t * 5 & -(t >> 7) | ~t");
+    assert_eq!(reprinted("t - -1", Style::Spaced), "t - -1");
+}
+
+/// xorshift, so the test needs no dependencies and always checks the same
+/// trees.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, n: u64) -> u64 {
+        self.next() % n
+    }
+}
+
+const BINOPS: [BinOp; 9] = [
+    BinOp::One(BinOp1::Mul),
+    BinOp::One(BinOp1::Div),
+    BinOp::Two(BinOp2::Add),
+    BinOp::Two(BinOp2::Sub),
+    BinOp::Three(BitShift::Left),
+    BinOp::Three(BitShift::Right),
+    BinOp::Four(BitAnd),
+    BinOp::Five(BitXOr),
+    BinOp::Six(BitOr),
+];
+
+const UNOPS: [UnOp; 3] = [UnOp::Neg, UnOp::BoolNot, UnOp::BitNot];
+
+fn arbitrary(rng: &mut Rng, depth: u32) -> Expr {
+    let kind = if depth == 0 { rng.below(3) } else { rng.below(6) };
+    match kind {
+        0 => Time,
+        1 => Num(Int(match rng.below(4) {
+            // the parser only makes literals of 0 and up
+            0 => 0,
+            1 => i32::MAX,
+            _ => (rng.next() % 1000) as i32,
+        })),
+        2 => Num(Float((rng.next() % 4000) as f32 / 8.0 + 0.5)),
+        3 => UnExpr(UNOPS[rng.below(3) as usize], Box::new(arbitrary(rng, depth - 1))),
+        _ => BinExpr(
+            Box::new(arbitrary(rng, depth - 1)),
+            BINOPS[rng.below(9) as usize],
+            Box::new(arbitrary(rng, depth - 1)),
+        ),
+    }
+}
+
+#[test]
+fn round_trip_property_test() {
+    let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+    for _ in 0..5000 {
+        let e = arbitrary(&mut rng, 6);
+        for &style in &[Style::Compact, Style::Spaced] {
+            let printed = print(&e, style);
+            assert_eq!(parse(&printed), Ok(e.clone()), "{}", printed);
+        }
+    }
+}
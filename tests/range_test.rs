@@ -0,0 +1,84 @@
+extern crate bbb_core;
+
+use bbb_core::eval::eval;
+use bbb_core::parser::parse;
+use bbb_core::range::*;
+
+fn analyzed(e: &str, lo: i32, hi: i32) -> Report {
+    analyze(&parse(e).unwrap(), Interval::new(lo, hi))
+}
+
+#[test]
+fn output_range() {
+    let report = analyzed("t & 255", 0, 1 << 20);
+    assert_eq!(report.output.unwrap().range, Interval::new(0, 255));
+    assert!(!report.fits_sample());
+
+    let report = analyzed("t >> 12 & 7", 0, 1 << 20);
+    assert_eq!(report.output.unwrap().range, Interval::new(0, 7));
+    assert!(report.fits_sample());
+
+    let report = analyzed("t * 2 + 1", 0, 100);
+    assert_eq!(report.output.unwrap().range, Interval::new(1, 201));
+    assert_eq!(report.output.unwrap().bits.ones & 1, 1);
+
+    let report = analyzed("~t", 0, 15);
+    assert_eq!(report.output.unwrap().range, Interval::new(-16, -1));
+}
+
+#[test]
+fn silent_output() {
+    assert!(analyzed("(t * 3) & 0", 0, 1 << 20).is_constant());
+    assert!(analyzed("(t << 8) & 255", 0, 1 << 20).is_constant());
+    assert!(!analyzed("t * 3", 0, 1 << 20).is_constant());
+}
+
+#[test]
+fn division_by_zero() {
+    let report = analyzed("t / (t >> 8 & 3)", 0, 1 << 16);
+    assert_eq!(report.divisions_by_zero, vec![vec![]]);
+
+    let report = analyzed("t * 2 | t / ((t >> 8 & 3) + 1)", 0, 1 << 16);
+    assert!(report.divisions_by_zero.is_empty());
+
+    let report = analyzed("(1 / 0) + t", 0, 1 << 16);
+    assert_eq!(report.divisions_by_zero, vec![vec![0]]);
+    assert_eq!(report.output, None);
+}
+
+#[test]
+fn overflow() {
+    let report = analyzed("t * t | 1", 0, 1 << 20);
+    assert_eq!(report.overflows, vec![vec![0]]);
+    assert_eq!(report.output.unwrap().range.hi, i32::MAX);
+    assert_eq!(report.output.unwrap().bits.ones, 1);
+
+    assert!(analyzed("t * t", 0, 1 << 15).overflows.is_empty());
+    assert_eq!(analyzed("1 << t", 0, 40).overflows, vec![vec![]]);
+}
+
+#[test]
+fn bounds_hold() {
+    let expressions = [
+        "(t * 9 & t >> 4 | t * 5 & t >> 7 | t * 3 & t / 1024) - 1",
+        "((t<<1)^((t<<1)+(t>>7)&t>>12))|t>>(4-(1^7&(t>>19)))|t>>7",
+        "-t ^ ~(t >> 3) | !t * 2",
+        "t / ((t >> 4 & 3) - 1) << 3",
+        "(t >> 3) - (t & -16) * 3",
+    ];
+
+    for e in expressions.iter() {
+        let expression = parse(e).unwrap();
+        let report = analyze(&expression, Interval::new(0, 1 << 16));
+        assert!(report.overflows.is_empty(), "{}", e);
+        let value = report.output.unwrap();
+
+        for t in 0..1 << 16 {
+            if let Ok(x) = eval(t, &expression) {
+                assert!(value.range.contains(x), "{} at t = {}: {} not in {:?}", e, t, x, value.range);
+                assert_eq!(x as u32 & value.bits.zeros, 0, "{} at t = {}", e, t);
+                assert_eq!(x as u32 & value.bits.ones, value.bits.ones, "{} at t = {}", e, t);
+            }
+        }
+    }
+}
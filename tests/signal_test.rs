@@ -0,0 +1,61 @@
+#![cfg(feature = "audio")]
+
+extern crate bbb_core;
+extern crate sample;
+
+use bbb_core::expr::Expr;
+use bbb_core::ops::UnOp;
+use bbb_core::parser::parse;
+use bbb_core::signal::ExprSignal;
+use sample::Signal;
+
+#[test]
+fn fill_matches_next() {
+    let e = parse("(t * 9 & t >> 4 | t * 5 & t >> 7) / (t >> 10 & 3)").unwrap();
+    let mut by_sample = ExprSignal::from(e.clone());
+    let mut by_block = ExprSignal::from(e);
+
+    let mut frames = [[0; 1]; 1000];
+    for _ in 0..10 {
+        by_block.fill(&mut frames);
+        for frame in frames.iter() {
+            assert_eq!(*frame, by_sample.next());
+        }
+    }
+    assert_eq!(by_block.time, by_sample.time);
+}
+
+#[test]
+fn fill_stops_at_the_first_error() {
+    // below 4, `2147483646 + (16 >> t)` overflows, but `1 / (t / 5)` has
+    // already failed, so `next` never gets to it
+    let e = parse("1 / (t / 5) + (2147483646 + (16 >> t))").unwrap();
+    let mut by_sample = ExprSignal::from(e.clone());
+    let mut by_block = ExprSignal::from(e);
+
+    let mut frames = [[0; 1]; 32];
+    by_block.fill(&mut frames);
+    for frame in frames.iter() {
+        assert_eq!(*frame, by_sample.next());
+    }
+}
+
+#[test]
+fn fill_handles_deep_expressions() {
+    let deep = || {
+        let mut e = Expr::Time;
+        for i in 0..20_000 {
+            let op = if i % 2 == 0 { UnOp::Neg } else { UnOp::BitNot };
+            e = Expr::UnExpr(op, Box::new(e));
+        }
+        e
+    };
+    let mut by_sample = ExprSignal::from(deep());
+    let mut by_block = ExprSignal::from(deep());
+
+    let mut frames = [[0; 1]; 256];
+    by_block.fill(&mut frames);
+    for frame in frames.iter() {
+        assert_eq!(*frame, by_sample.next());
+    }
+}
@@ -0,0 +1,302 @@
+extern crate bbb_core;
+
+use bbb_core::expr::Expr;
+use bbb_core::expr::Expr::*;
+use bbb_core::parser::{parse_with_comments, Expected, Span, MAX_DEPTH};
+use bbb_core::syntax::*;
+
+/// What the syntax tree is checked against: the parser, reading comments.
+fn commented(source: &str) -> Result<Expr, String> {
+    parse_with_comments(source).map(|(expression, _)| expression)
+}
+
+const SOURCES: [&str; 9] = [
+    "t",
+    "  t*5&t>>7  ",
+    "((t<<1)^((t<<1)+(t>>7)&t>>12))|t>>(4-(1^7&(t>>19)))|t>>7",
+    "// kick\r\nt * 5 & t >> 7 // melody\n| t >> 4 // bass",
+    "-(t - -1) / 2.5 + ~T",
+    "( ( t ) )",
+    "!\t-t\n",
+    "t - (1 - 2) - 3",
+    "t -1 - - 5 * --t | -2147483647",
+];
+
+#[test]
+fn lossless_test() {
+    for source in &SOURCES {
+        let tree = parse_syntax(source).unwrap();
+        assert_eq!(tree.to_string(), *source);
+        assert_eq!(tree.to_expr(), commented(source), "{}", source);
+    }
+}
+
+#[test]
+fn same_errors_test() {
+    let invalid = ["", "t +", "(t", "t)", "t t", "t * / 2", "-", "// t", "t $ 1", "t<", "t<5"];
+    for source in &invalid {
+        assert!(parse_syntax(source).is_err(), "{}", source);
+        assert_eq!(parse_syntax(source).err(), commented(source).err(), "{}", source);
+    }
+
+    let e = format!("{}t", "~".repeat(MAX_DEPTH));
+    assert_eq!(parse_syntax(&e).err(), commented(&e).err());
+    let e = format!("{}t", "~".repeat(MAX_DEPTH - 1));
+    assert!(parse_syntax(&e).is_ok());
+
+    let n = 100_000;
+    let e = format!("{}t{}", "(".repeat(n), ")".repeat(n));
+    let tree = parse_syntax(&e).unwrap();
+    assert_eq!(tree.root.open.len(), n);
+    assert_eq!(tree.to_expr(), Ok(Time));
+}
+
+#[test]
+fn tokens_test() {
+    let tree = parse_syntax(" (t + 1) // x").unwrap();
+    let root = &tree.root;
+    assert_eq!(root.span(), Span { start: 1, end: 8 });
+    assert_eq!(root.open.len(), 1);
+    assert_eq!(root.open[0].leading[0].kind, TokenKind::Whitespace);
+    assert_eq!(root.close[0].token.span, Span { start: 7, end: 8 });
+
+    match root.kind {
+        NodeKind::Binary(ref left, ref op, ref right) => {
+            assert_eq!(left.span(), Span { start: 2, end: 3 });
+            assert_eq!(op.token.text, "+");
+            assert_eq!(op.token.kind, TokenKind::Operator(OperatorClass::Arithmetic));
+            assert_eq!(op.leading.len(), 1);
+            assert_eq!(right.to_string(), " 1");
+        }
+        _ => panic!("expected a binary node"),
+    }
+
+    let kinds: Vec<TokenKind> = tree.trailing.iter().map(|token| token.kind).collect();
+    assert_eq!(kinds, vec![TokenKind::Whitespace, TokenKind::Comment]);
+    assert_eq!(tree.trailing[1].text, "// x");
+}
+
+#[test]
+fn edit_test() {
+    let source = "t*5 & // mask\n  t>>7";
+    let mut tree = parse_syntax(source).unwrap();
+    if let NodeKind::Binary(ref mut left, _, _) = tree.root.kind {
+        if let NodeKind::Binary(_, _, ref mut right) = left.kind {
+            if let NodeKind::Number(ref mut number) = right.kind {
+                number.token.text = "3".to_owned();
+            }
+        }
+    }
+    assert_eq!(tree.to_string(), "t*3 & // mask\n  t>>7");
+    assert_eq!(tree.to_expr(), commented("t*3&t>>7"));
+
+    if let NodeKind::Binary(_, ref mut op, _) = tree.root.kind {
+        op.token.text = "?".to_owned();
+    }
+    assert!(tree.to_expr().is_err());
+}
+
+fn kinds(input: &str) -> Vec<(TokenKind, &str)> {
+    tokenize(input)
+        .into_iter()
+        .map(|token| (token.kind, &input[token.span.start..token.span.end]))
+        .collect()
+}
+
+#[test]
+fn tokenize_test() {
+    use bbb_core::syntax::OperatorClass::*;
+    use bbb_core::syntax::TokenKind::*;
+
+    assert_eq!(
+        kinds("-(t*-5)>>2.5|~t // x"),
+        vec![
+            (Operator(Unary), "-"),
+            (OpenParen, "("),
+            (Time, "t"),
+            (Operator(Arithmetic), "*"),
+            (Operator(Unary), "-"),
+            (Number, "5"),
+            (CloseParen, ")"),
+            (Operator(Shift), ">>"),
+            (Number, "2.5"),
+            (Operator(Bitwise), "|"),
+            (Operator(Unary), "~"),
+            (Time, "t"),
+            (Whitespace, " "),
+            (Comment, "// x"),
+        ]
+    );
+    assert_eq!(
+        kinds("t-5 - -5"),
+        vec![
+            (Time, "t"),
+            (Operator(Arithmetic), "-"),
+            (Number, "5"),
+            (Whitespace, " "),
+            (Operator(Arithmetic), "-"),
+            (Whitespace, " "),
+            (Operator(Unary), "-"),
+            (Number, "5"),
+        ]
+    );
+    assert_eq!(kinds("-2147483648"), vec![(Operator(Unary), "-"), (Error, "2147483648")]);
+    assert_eq!(kinds("--t"), vec![(Operator(Unary), "-"), (Operator(Unary), "-"), (Time, "t")]);
+}
+
+#[test]
+fn tokenize_errors_test() {
+    use bbb_core::syntax::TokenKind::*;
+
+    assert_eq!(kinds(""), vec![]);
+    assert_eq!(
+        kinds("sin(t)$é<t"),
+        vec![
+            (Identifier, "sin"),
+            (OpenParen, "("),
+            (Time, "t"),
+            (CloseParen, ")"),
+            (Error, "$"),
+            (Error, "é"),
+            (Error, "<"),
+            (Time, "t"),
+        ]
+    );
+    assert_eq!(kinds("99999999999 1.2.3"), vec![(Error, "99999999999"), (Whitespace, " "), (Error, "1.2.3")]);
+    assert_eq!(kinds("tt"), vec![(Identifier, "tt")]);
+}
+
+#[test]
+fn tokenize_matches_syntax_tree_test() {
+    for source in &SOURCES {
+        let tree = parse_syntax(source).unwrap();
+        let expected: Vec<Token> = tree.tokens().into_iter().cloned().collect();
+        assert_eq!(tokenize(source), expected, "{}", source);
+    }
+}
+
+#[test]
+fn tokenize_never_fails_test() {
+    // xorshift over a small alphabet, so most inputs are nearly valid
+    let alphabet: Vec<char> = "t5.-+*/<>&^|!~() \n/xé$".chars().collect();
+    let mut state = 0x2545_f491_4f6c_dd1du64;
+    for _ in 0..2000 {
+        let mut input = String::new();
+        for _ in 0..(state % 24) {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            input.push(alphabet[(state % alphabet.len() as u64) as usize]);
+        }
+        let tokens = tokenize(&input);
+        let text: String = tokens.iter().map(|token| &*token.text).collect();
+        assert_eq!(text, input);
+        assert!(tokens.windows(2).all(|pair| pair[0].span.end == pair[1].span.start));
+    }
+}
+
+fn recovered(input: &str) -> Vec<(String, Span)> {
+    let (tree, errors) = parse_recovering(input);
+    assert_eq!(tree.to_string(), input);
+    errors.into_iter().map(|error| (error.message, error.span)).collect()
+}
+
+#[test]
+fn recovering_test() {
+    let source = "t + * 5 $ (t>>";
+    let (tree, errors) = parse_recovering(source);
+    assert_eq!(tree.to_string(), source);
+    assert!(tree.to_expr().is_err());
+    let found: Vec<(&str, Span, Option<Expected>)> = errors
+        .iter()
+        .map(|error| (&*error.message, error.span, error.expected))
+        .collect();
+    assert_eq!(
+        found,
+        vec![
+            (
+                "expected `t`, a number, `(` or a unary operator at offset 4",
+                Span { start: 4, end: 4 },
+                Some(Expected::Operand),
+            ),
+            (
+                "unexpected input at offset 8",
+                Span { start: 8, end: 9 },
+                Some(Expected::Operator { open_parens: 0 }),
+            ),
+            ("incomplete expression", Span { start: 14, end: 14 }, Some(Expected::Operand)),
+        ]
+    );
+    assert_eq!(Some(errors[0].message.clone()), commented(source).err());
+
+    // the junk between `5` and `(t>>` stands for an operator
+    match tree.root.kind {
+        NodeKind::Binary(_, ref op, _) => {
+            assert_eq!(op.token.text, "$");
+            assert_eq!(op.token.kind, TokenKind::Error);
+        }
+        _ => panic!("expected a binary node"),
+    }
+
+    assert_eq!(
+        recovered("t) + (t"),
+        vec![
+            ("unmatched `)` at offset 1".to_owned(), Span { start: 1, end: 2 }),
+            ("incomplete expression".to_owned(), Span { start: 7, end: 7 }),
+        ]
+    );
+    assert_eq!(
+        recovered("sin(t) t"),
+        vec![
+            (
+                "expected `t`, a number, `(` or a unary operator at offset 0".to_owned(),
+                Span { start: 0, end: 3 },
+            ),
+            ("unexpected input at offset 3".to_owned(), Span { start: 3, end: 3 }),
+            ("unexpected input at offset 7".to_owned(), Span { start: 7, end: 7 }),
+        ]
+    );
+    assert_eq!(recovered("t <"), vec![("incomplete expression".to_owned(), Span { start: 3, end: 3 })]);
+
+    let e = format!("{}t + $", "~".repeat(MAX_DEPTH));
+    let found = recovered(&e);
+    assert_eq!(Some(found[0].0.clone()), commented(&e).err());
+    assert_eq!(found[0].1, Span { start: 0, end: MAX_DEPTH + 1 });
+    assert_eq!(found.len(), 2);
+
+    // the comment before a node that is too deep stays out of its error token
+    let e = format!("// deep\n {}t", "~".repeat(MAX_DEPTH));
+    let (tree, errors) = parse_recovering(&e);
+    let span = Span { start: 9, end: e.len() };
+    assert_eq!(errors[0].span, span);
+    let tokens = tree.tokens();
+    assert_eq!(tokens.len(), 3);
+    assert_eq!(tokens[0].kind, TokenKind::Comment);
+    assert_eq!(tokens[1].kind, TokenKind::Whitespace);
+    assert_eq!(tokens[2].kind, TokenKind::Error);
+    assert_eq!(tokens[2].span, span);
+    assert_eq!(tokens[2].text, &e[9..]);
+    assert_eq!(tree.to_string(), e);
+}
+
+#[test]
+fn recovering_agrees_with_parse_test() {
+    let alphabet: Vec<char> = "t5.-+*/<>&^|!~() \n/xé$".chars().collect();
+    let mut state = 0x9e37_79b9_7f4a_7c15u64;
+    for _ in 0..3000 {
+        let mut input = String::new();
+        for _ in 0..(state % 24) {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            input.push(alphabet[(state % alphabet.len() as u64) as usize]);
+        }
+        let (tree, errors) = parse_recovering(&input);
+        assert_eq!(tree.to_string(), input);
+        assert_eq!(errors.first().map(|error| error.message.clone()), commented(&input).err(), "{}", input);
+        if errors.is_empty() {
+            assert_eq!(tree.to_expr(), commented(&input), "{}", input);
+        }
+        assert!(errors.windows(2).all(|pair| pair[0].span.start <= pair[1].span.start), "{}", input);
+    }
+}